@@ -0,0 +1,58 @@
+// A busy server's socket table: mostly ownerless TIME_WAIT churn, plus
+// thousands of connections held by a couple of processes. The owners are
+// this process and init, so the process lookups are real.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process;
+
+use plug::sockets::SocketRecord;
+use plug::{CollectOptions, Manager, ProtocolInfo, SocketState};
+
+const TIME_WAIT: usize = 25_000;
+const CONNECTIONS: usize = 5_000;
+
+fn busy_server() -> Vec<SocketRecord> {
+    let local = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let peer = |i: usize| {
+        SocketAddr::new(
+            IpAddr::V4(Ipv4Addr::new(10, 1, (i / 256) as u8, (i % 256) as u8)),
+            40_000 + (i % 20_000) as u16,
+        )
+    };
+    let owners = [process::id(), 1];
+
+    let time_wait = (0..TIME_WAIT).map(|i| SocketRecord {
+        local_addr: local,
+        local_port: 443,
+        remote: Some(peer(i)),
+        protocol: ProtocolInfo::TCP,
+        state: SocketState::TimeWait,
+        pids: vec![],
+        inode: None,
+    });
+    let connections = (0..CONNECTIONS).map(|i| SocketRecord {
+        local_addr: local,
+        local_port: 8000 + (i % 50) as u16,
+        remote: Some(peer(i)),
+        protocol: ProtocolInfo::TCP,
+        state: SocketState::Established,
+        pids: vec![owners[i % owners.len()]],
+        inode: Some(i as u32),
+    });
+    time_wait.chain(connections).collect()
+}
+
+fn collect(c: &mut Criterion) {
+    let sockets = busy_server();
+    c.bench_function("collect 30k sockets", |b| {
+        b.iter(|| {
+            Manager::collect(black_box(sockets.clone()), CollectOptions::default())
+                .expect("a fixed socket list always reads")
+        })
+    });
+}
+
+criterion_group!(benches, collect);
+criterion_main!(benches);
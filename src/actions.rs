@@ -0,0 +1,410 @@
+// What the interactive menu offers for a picked socket. Each entry is an
+// `Action`, the menu lists the ones in `builtin` that apply to the pick.
+
+use core::fmt;
+use inquire::Confirm;
+use plug::cli::DetailsArgs;
+use plug::{KillOutcome, KillStatus, Manager, PortInfo};
+#[cfg(unix)]
+use sysinfo::ProcessStatus;
+use sysinfo::{Pid, Process, ProcessesToUpdate, Signal};
+
+#[cfg(unix)]
+use crate::renice_process;
+use crate::{
+    answered, copy_to_clipboard, display_children, display_details, display_environment,
+    display_open_files, display_tree, offer_relaunch, open_in_browser, pick_and_send_signal,
+    print_kill_outcomes, print_kill_plan, report_freed_ports, restart_process, send_signal,
+};
+
+pub trait Action {
+    fn label(&self) -> &'static str;
+
+    // Whether the menu offers this action for `picked`.
+    fn applies_to(&self, _picked: &PortInfo) -> bool {
+        true
+    }
+
+    // Actions that end the process ask for its name when it's protected.
+    fn kills(&self) -> bool {
+        false
+    }
+
+    // Loads what `execute` shows or needs, before the process is looked up.
+    fn prepare(&self, _manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {}
+
+    fn execute(&self, context: &mut Context) -> ActionResult;
+}
+
+impl fmt::Display for dyn Action {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.label())
+    }
+}
+
+// The picked entry is a copy, kills rescan and replace `port_infos`.
+pub struct Context<'a> {
+    pub manager: &'a mut Manager,
+    pub picked: PortInfo,
+    pub details: &'a DetailsArgs,
+}
+
+impl Context<'_> {
+    // `handle_event` checks that the process is still there before any
+    // action runs.
+    fn process(&self) -> &Process {
+        self.manager
+            .system_info
+            .process(Pid::from_u32(self.picked.pid))
+            .expect("the picked process was looked up before the action")
+    }
+}
+
+#[derive(PartialEq)]
+pub enum ActionResult {
+    Done,
+    PidReused, // the pid changed hands, the caller offers a rescan of the port
+}
+
+// In menu order.
+pub fn builtin() -> Vec<Box<dyn Action>> {
+    vec![
+        Box::new(Kill),
+        Box::new(KillTree),
+        Box::new(Restart),
+        Box::new(SendSignal),
+        #[cfg(unix)]
+        Box::new(Renice),
+        Box::new(ViewDetails),
+        Box::new(ViewOpenFiles),
+        Box::new(ViewEnvironment),
+        Box::new(ViewChildren),
+        Box::new(ViewTree),
+        Box::new(CopyPid),
+        Box::new(CopyRow),
+        Box::new(OpenInBrowser),
+        #[cfg(unix)]
+        Box::new(Resume),
+        #[cfg(unix)]
+        Box::new(Suspend),
+    ]
+}
+
+fn refresh_details(manager: &mut Manager, pid: u32) {
+    manager.refresh_process_details(ProcessesToUpdate::Some(&[Pid::from_u32(pid)]));
+}
+
+// What every kill from the menu reports afterwards.
+fn finish_kill(manager: &mut Manager, outcomes: &[KillOutcome]) -> ActionResult {
+    print_kill_outcomes(outcomes);
+    report_freed_ports(manager, outcomes);
+    offer_relaunch(outcomes);
+    if outcomes
+        .iter()
+        .any(|outcome| outcome.status == KillStatus::Reused)
+    {
+        ActionResult::PidReused
+    } else {
+        ActionResult::Done
+    }
+}
+
+struct Kill;
+
+impl Action for Kill {
+    fn label(&self) -> &'static str {
+        "Kill"
+    }
+
+    fn kills(&self) -> bool {
+        true
+    }
+
+    fn prepare(&self, manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {
+        manager.load_launch_details();
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        let outcome = context.manager.kill_process_by_pid(
+            context.picked.pid,
+            context.process(),
+            Signal::Kill,
+            false,
+        );
+        finish_kill(context.manager, &[outcome])
+    }
+}
+
+struct KillTree;
+
+impl Action for KillTree {
+    fn label(&self) -> &'static str {
+        "Kill Process Tree"
+    }
+
+    fn kills(&self) -> bool {
+        true
+    }
+
+    fn prepare(&self, manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {
+        manager.load_launch_details();
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        let pid = context.picked.pid;
+        // the tree is rarely just the one process that was picked
+        let plan = context
+            .manager
+            .kill_process_tree(pid, Signal::Kill, true, false);
+        if print_kill_plan(context.manager, &plan) > 1
+            && !matches!(
+                answered(Confirm::new("Go ahead?").with_default(false).prompt()),
+                Some(true)
+            )
+        {
+            return ActionResult::Done;
+        }
+        let outcomes = context
+            .manager
+            .kill_process_tree(pid, Signal::Kill, false, false);
+        finish_kill(context.manager, &outcomes)
+    }
+}
+
+struct Restart;
+
+impl Action for Restart {
+    fn label(&self) -> &'static str {
+        "Restart"
+    }
+
+    fn kills(&self) -> bool {
+        true
+    }
+
+    fn prepare(&self, manager: &mut Manager, pid: u32, _details: &DetailsArgs) {
+        refresh_details(manager, pid);
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        restart_process(context.manager, context.picked.pid, context.process());
+        ActionResult::Done
+    }
+}
+
+struct SendSignal;
+
+impl Action for SendSignal {
+    fn label(&self) -> &'static str {
+        "Send Signal…"
+    }
+
+    fn kills(&self) -> bool {
+        true
+    }
+
+    fn prepare(&self, manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {
+        manager.load_launch_details();
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        pick_and_send_signal(context.manager, context.process());
+        ActionResult::Done
+    }
+}
+
+#[cfg(unix)]
+struct Renice;
+
+#[cfg(unix)]
+impl Action for Renice {
+    fn label(&self) -> &'static str {
+        "Renice"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        renice_process(context.picked.pid);
+        ActionResult::Done
+    }
+}
+
+struct ViewDetails;
+
+impl Action for ViewDetails {
+    fn label(&self) -> &'static str {
+        "View Details"
+    }
+
+    fn prepare(&self, manager: &mut Manager, pid: u32, details: &DetailsArgs) {
+        refresh_details(manager, pid);
+        manager.sample_cpu_usage(&[pid]);
+        if details.resolve {
+            manager.resolve_peers(pid);
+        }
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        display_details(
+            context.manager,
+            &context.picked,
+            context.process(),
+            context.details,
+        );
+        ActionResult::Done
+    }
+}
+
+struct ViewOpenFiles;
+
+impl Action for ViewOpenFiles {
+    fn label(&self) -> &'static str {
+        "View Open Files"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        display_open_files(context.manager, context.picked.pid);
+        ActionResult::Done
+    }
+}
+
+struct ViewEnvironment;
+
+impl Action for ViewEnvironment {
+    fn label(&self) -> &'static str {
+        "View Environment"
+    }
+
+    fn prepare(&self, manager: &mut Manager, pid: u32, _details: &DetailsArgs) {
+        refresh_details(manager, pid);
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        display_environment(context.process());
+        ActionResult::Done
+    }
+}
+
+struct ViewChildren;
+
+impl Action for ViewChildren {
+    fn label(&self) -> &'static str {
+        "View Child Processes"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        display_children(context.manager, context.picked.pid);
+        ActionResult::Done
+    }
+}
+
+struct ViewTree;
+
+impl Action for ViewTree {
+    fn label(&self) -> &'static str {
+        "View Process Tree"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        display_tree(context.manager, context.picked.pid);
+        ActionResult::Done
+    }
+}
+
+struct CopyPid;
+
+impl Action for CopyPid {
+    fn label(&self) -> &'static str {
+        "Copy PID"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        copy_to_clipboard(&context.picked.pid.to_string());
+        ActionResult::Done
+    }
+}
+
+struct CopyRow;
+
+impl Action for CopyRow {
+    fn label(&self) -> &'static str {
+        "Copy Row"
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        copy_to_clipboard(&context.picked.to_string());
+        ActionResult::Done
+    }
+}
+
+struct OpenInBrowser;
+
+impl Action for OpenInBrowser {
+    fn label(&self) -> &'static str {
+        "Open in Browser"
+    }
+
+    fn applies_to(&self, picked: &PortInfo) -> bool {
+        picked.is_listening()
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        open_in_browser(&context.picked.browser_url());
+        ActionResult::Done
+    }
+}
+
+#[cfg(unix)]
+struct Suspend;
+
+#[cfg(unix)]
+impl Action for Suspend {
+    fn label(&self) -> &'static str {
+        "Suspend"
+    }
+
+    fn applies_to(&self, picked: &PortInfo) -> bool {
+        picked.process_status != ProcessStatus::Stop
+    }
+
+    fn prepare(&self, manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {
+        manager.load_launch_details();
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        send_signal(
+            context.manager,
+            context.process(),
+            Signal::Stop,
+            "suspended",
+        );
+        ActionResult::Done
+    }
+}
+
+#[cfg(unix)]
+struct Resume;
+
+#[cfg(unix)]
+impl Action for Resume {
+    fn label(&self) -> &'static str {
+        "Resume"
+    }
+
+    fn applies_to(&self, picked: &PortInfo) -> bool {
+        picked.process_status == ProcessStatus::Stop
+    }
+
+    fn prepare(&self, manager: &mut Manager, _pid: u32, _details: &DetailsArgs) {
+        manager.load_launch_details();
+    }
+
+    fn execute(&self, context: &mut Context) -> ActionResult {
+        send_signal(
+            context.manager,
+            context.process(),
+            Signal::Continue,
+            "resumed",
+        );
+        ActionResult::Done
+    }
+}
@@ -0,0 +1,645 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use core::fmt;
+use regex::Regex;
+use std::num::NonZeroUsize;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process;
+use sysinfo::Signal;
+
+use crate::output::OutputFormat;
+use crate::{KillRequest, PortInfo, ProtocolInfo, SortKey};
+
+#[derive(Parser)]
+#[command(version, about = "Find and manage the processes behind your ports")]
+pub struct Cli {
+    /// With no subcommand, plug shows the interactive menu
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Order the entries by this column
+    #[arg(long, value_enum, global = true)]
+    pub sort: Option<SortKey>,
+
+    /// Show only the first N entries, after sorting; the interactive menu
+    /// shows at most 1000 unless this is set
+    #[arg(long, global = true, value_name = "N")]
+    pub limit: Option<NonZeroUsize>,
+
+    /// Disable colored output, same as setting NO_COLOR
+    #[arg(long, global = true)]
+    pub no_color: bool,
+
+    /// Print the entries in this format instead of showing the interactive menu
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<OutputFormat>,
+
+    /// Write the entries to this file instead of stdout, replacing it atomically
+    #[arg(long, global = true, value_name = "PATH")]
+    pub output_file: Option<PathBuf>,
+
+    /// Add the remote address and the start time to each entry
+    #[arg(long, global = true)]
+    pub wide: bool,
+
+    /// Show port numbers without looking up their service names
+    #[arg(short, long, global = true)]
+    pub numeric: bool,
+
+    /// Show start times in UTC instead of the local timezone
+    #[arg(long, global = true)]
+    pub utc: bool,
+
+    /// Print the traffic of each network interface above the list
+    #[arg(long, global = true)]
+    pub net: bool,
+
+    /// Don't record sent signals in the history log
+    #[arg(long, global = true)]
+    pub no_log: bool,
+
+    /// Ask for a name or port to narrow the menu down before showing it
+    #[arg(long)]
+    pub interactive_filter: bool,
+
+    #[command(flatten)]
+    pub details: DetailsArgs,
+}
+
+impl Cli {
+    // The display flags are global so they can go anywhere on the line. Says
+    // which one was given to a subcommand that has no use for it, e.g.
+    // "--sort has no effect on `plug kill`".
+    pub fn unused_display_flag(&self) -> Option<String> {
+        let (subcommand, used): (&str, &[&str]) = match &self.command {
+            None | Some(Command::List { .. }) | Some(Command::Watch { .. }) => return None,
+            Some(Command::Info(_)) => {
+                ("info", &["--format", "--output-file", "--numeric", "--utc"])
+            }
+            Some(Command::Tui { .. }) => ("tui", &["--sort", "--numeric", "--utc"]),
+            Some(Command::Snapshot { .. }) => ("snapshot", &["--numeric", "--utc"]),
+            Some(Command::Kill(_)) => ("kill", &[]),
+            Some(Command::Wait(_)) => ("wait", &[]),
+            Some(Command::History { .. }) => ("history", &[]),
+            Some(Command::Completions { .. }) => ("completions", &[]),
+        };
+        let (flag, _) = [
+            ("--sort", self.sort.is_some()),
+            ("--limit", self.limit.is_some()),
+            ("--format", self.format.is_some()),
+            ("--output-file", self.output_file.is_some()),
+            ("--wide", self.wide),
+            ("--numeric", self.numeric),
+            ("--utc", self.utc),
+            ("--net", self.net),
+        ]
+        .into_iter()
+        .find(|(flag, given)| *given && !used.contains(flag))?;
+        Some(format!("{} has no effect on `plug {}`", flag, subcommand))
+    }
+}
+
+// Extra sections for the "View Details" action.
+#[derive(Args, Default)]
+pub struct DetailsArgs {
+    /// List every pid that shares the selected socket, e.g. forked workers
+    #[arg(long)]
+    pub all_pids: bool,
+
+    /// List every socket of the process instead of the first 20
+    #[arg(long = "all")]
+    pub all_sockets: bool,
+
+    /// Look up the host names of connected peers, waiting at most two seconds
+    #[arg(long)]
+    pub resolve: bool,
+
+    /// Also print the process's environment variables, which may hold secrets
+    #[arg(long)]
+    pub show_env: bool,
+
+    /// Mask the values of variables that look like secrets, e.g. *TOKEN* or *KEY*
+    #[arg(long, requires = "show_env")]
+    pub redact: bool,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the sockets matching the given filters
+    List {
+        #[command(flatten)]
+        filters: Filters,
+
+        /// Only report ports that more than one process is listening on
+        #[arg(long)]
+        conflicts: bool,
+
+        /// Count the sockets per protocol and state instead of listing them
+        #[arg(long, conflicts_with = "conflicts")]
+        stats: bool,
+    },
+    /// Kill the processes behind a port, a pid, or a process name
+    Kill(KillArgs),
+    /// Print the details of the processes behind a port or a pid
+    #[command(visible_alias = "details")]
+    Info(InfoArgs),
+    /// Browse the sockets in a full-screen table that refreshes itself
+    Tui {
+        #[command(flatten)]
+        filters: Filters,
+
+        #[command(flatten)]
+        details: DetailsArgs,
+    },
+    /// Block until no process holds a port, or until a server listens on it
+    Wait(WaitArgs),
+    /// Show the signals plug sent, most recent last
+    History {
+        /// How many entries to show, e.g. `plug history 50`
+        #[arg(default_value_t = 20)]
+        count: usize,
+    },
+    /// Save the current sockets, or compare against the saved ones
+    Snapshot {
+        #[arg(value_enum)]
+        action: SnapshotAction,
+
+        /// Where the snapshot is kept, by default next to the history log
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Print a completion script, e.g. `plug completions zsh > ~/.zfunc/_plug`
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Redraw the list every few seconds until interrupted
+    Watch {
+        #[command(flatten)]
+        filters: Filters,
+
+        /// Seconds to wait between refreshes
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SnapshotAction {
+    /// Write every socket holder to the snapshot file
+    Save,
+    /// Print the holders that appeared or went away since the last save
+    Diff,
+}
+
+#[derive(Debug, Args, Default, Clone)]
+pub struct Filters {
+    /// Only show sockets on these ports, e.g. `--port 80,443` or `--port 80 --port 443`
+    #[arg(long = "port", value_name = "PORT", value_delimiter = ',')]
+    pub ports: Vec<u16>,
+
+    /// Only show sockets owned by this pid
+    #[arg(long)]
+    pub pid: Option<u32>,
+
+    /// Only show processes whose name contains this text
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Only show processes owned by this user
+    #[arg(long)]
+    pub user: Option<String>,
+
+    /// Only show sockets using this protocol
+    #[arg(long, value_enum, ignore_case = true)]
+    pub protocol: Option<ProtocolInfo>,
+
+    /// Only show TCP sockets in the LISTEN state
+    #[arg(long)]
+    pub listening: bool,
+
+    /// Only show sockets reachable from other machines, i.e. not bound to loopback
+    #[arg(long)]
+    pub exposed: bool,
+
+    /// Only show processes started less than this long ago, e.g. 30s, 5m or 1h30m
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub since: Option<u64>,
+
+    /// Hide processes whose name contains this text, can be repeated
+    #[arg(long = "exclude-name", value_name = "PATTERN")]
+    pub exclude_names: Vec<String>,
+
+    /// Hide the sockets of plug itself, always on for kill
+    #[arg(long)]
+    pub self_exclude: bool,
+}
+
+impl Filters {
+    pub fn matches(&self, info: &PortInfo) -> bool {
+        if !self.ports.is_empty() && !self.ports.contains(&info.port_number) {
+            return false;
+        }
+        if self.pid.is_some_and(|pid| pid != info.pid) {
+            return false;
+        }
+        if let Some(name) = &self.name
+            && !info.process_name.contains(name.as_str())
+        {
+            return false;
+        }
+        if self.user.as_ref().is_some_and(|user| *user != info.user) {
+            return false;
+        }
+        if self
+            .protocol
+            .is_some_and(|protocol| protocol != info.protocol)
+        {
+            return false;
+        }
+        if self.listening && !info.is_listening() {
+            return false;
+        }
+        // IPv4-mapped addresses like ::ffff:127.0.0.1 are loopback too
+        if self.exposed && info.local_addr.to_canonical().is_loopback() {
+            return false;
+        }
+        if self.since.is_some_and(|since| info.run_time >= since) {
+            return false;
+        }
+        // exclusions run last so `--name node --exclude-name helper` narrows
+        // the included set rather than competing with it
+        if self
+            .exclude_names
+            .iter()
+            .any(|pattern| info.process_name.contains(pattern.as_str()))
+        {
+            return false;
+        }
+        if self.self_exclude && info.pid == process::id() {
+            return false;
+        }
+
+        true
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.ports.is_empty()
+            || self.pid.is_some()
+            || self.name.is_some()
+            || self.user.is_some()
+            || self.protocol.is_some()
+            || self.listening
+            || self.exposed
+            || self.since.is_some()
+            || !self.exclude_names.is_empty()
+            || self.self_exclude
+    }
+
+    // Reads like "no tcp sockets in state LISTEN matched --name foo", used
+    // when nothing survives the filters.
+    pub fn describe_no_matches(&self) -> String {
+        let mut message = String::from("no ");
+        if let Some(protocol) = self.protocol {
+            message.push_str(&format!("{} ", protocol));
+        }
+        message.push_str("sockets");
+        if self.listening {
+            message.push_str(" in state LISTEN");
+        }
+
+        let mut flags = Vec::new();
+        if self.exposed {
+            flags.push(String::from("--exposed"));
+        }
+        if !self.ports.is_empty() {
+            let ports: Vec<String> = self.ports.iter().map(|port| port.to_string()).collect();
+            flags.push(format!("--port {}", ports.join(",")));
+        }
+        if let Some(pid) = self.pid {
+            flags.push(format!("--pid {}", pid));
+        }
+        if let Some(name) = &self.name {
+            flags.push(format!("--name {}", name));
+        }
+        if let Some(user) = &self.user {
+            flags.push(format!("--user {}", user));
+        }
+        if let Some(since) = self.since {
+            flags.push(format!("--since {}s", since));
+        }
+        for pattern in &self.exclude_names {
+            flags.push(format!("--exclude-name {}", pattern));
+        }
+        if self.self_exclude {
+            flags.push(String::from("--self-exclude"));
+        }
+        if !flags.is_empty() {
+            message.push_str(" matched ");
+            message.push_str(&flags.join(" "));
+        } else {
+            message.push_str(" found");
+        }
+        message
+    }
+}
+
+// Parses durations like "90", "30s", "5m" or "1h 30m" into seconds, the units
+// `human_readable_date` prints (d, h, m, s). A bare number means seconds.
+fn parse_duration(text: &str) -> Result<u64, String> {
+    let text: String = text.split_whitespace().collect();
+    if text.is_empty() {
+        return Err(String::from("the duration is empty"));
+    }
+    if let Ok(secs) = text.parse() {
+        return Ok(secs);
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(format!("unknown unit {:?}, use s, m, h or d", c)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("expected a number before {:?}", c))?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or("the duration is too long")?;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!("{} is missing a unit, use s, m, h or d", number));
+    }
+
+    Ok(total)
+}
+
+#[derive(Args)]
+pub struct WaitArgs {
+    /// Port to wait for
+    pub port: u16,
+
+    /// Wait until something listens on the port instead of until it is free
+    #[arg(long)]
+    pub until_listening: bool,
+
+    /// Give up after this many seconds and exit with status 124
+    #[arg(long)]
+    pub timeout: Option<u64>,
+
+    /// Seconds to wait between scans
+    #[arg(long, default_value_t = 1)]
+    pub interval: u64,
+}
+
+#[derive(Args)]
+pub struct InfoArgs {
+    #[command(flatten)]
+    pub target: InfoTarget,
+
+    #[command(flatten)]
+    pub details: DetailsArgs,
+
+    /// Print a JSON array with one object per process, same as --format json;
+    /// use --format json-pretty for indented output
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct InfoTarget {
+    /// Port whose processes to describe
+    pub port: Option<u16>,
+
+    /// Describe this pid, even if it holds no sockets
+    #[arg(long)]
+    pub pid: Option<u32>,
+}
+
+#[derive(Args)]
+pub struct KillArgs {
+    #[command(flatten)]
+    pub target: KillTarget,
+
+    /// Also kill all descendants of each process, children first
+    #[arg(long)]
+    pub tree: bool,
+
+    /// Relaunch each process with its original command line once it exits
+    #[arg(long, conflicts_with = "tree")]
+    pub restart: bool,
+
+    /// Send this signal instead of KILL, e.g. TERM to ask nicely, HUP or USR1 to reload or STOP to suspend
+    #[arg(long, value_enum, ignore_case = true, conflicts_with = "restart")]
+    pub signal: Option<KillSignal>,
+
+    /// Allow killing protected processes such as init, sshd, plug's own shell,
+    /// or anything listed under `protected` in the config
+    #[arg(long, alias = "i-know-what-im-doing")]
+    pub allow_protected: bool,
+
+    /// Treat --name as a regular expression, e.g. `^node$` for exact matches
+    #[arg(long, requires = "name")]
+    pub name_regex: bool,
+
+    /// Keep rescanning for up to this many seconds until the killed processes' ports are free
+    #[arg(long, value_name = "SECS")]
+    pub wait_free: Option<u64>,
+
+    /// Only kill processes that have been running longer than this, e.g. 2h,
+    /// to clear stale servers but spare freshly restarted ones
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, alias = "kill-older-than")]
+    pub older_than: Option<u64>,
+
+    /// Print what would be killed without sending any signal
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Exit with status 1 if any of the given ports was already free
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Skip the confirmation prompt enabled by `confirm_kill` in the config
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+impl KillArgs {
+    // What the library needs to know to carry the kill out. Fails on an
+    // invalid --name-regex.
+    pub fn request(&self) -> Result<KillRequest, regex::Error> {
+        Ok(KillRequest {
+            ports: self.target.port_list(),
+            pid: self.target.pid,
+            name: self.name_pattern().transpose()?,
+            older_than: self.older_than,
+            signal: self.signal.map_or(Signal::Kill, KillSignal::to_signal),
+            tree: self.tree,
+            allow_protected: self.allow_protected,
+            dry_run: self.dry_run,
+        })
+    }
+
+    // --name as a regex either way, plain text is escaped so it matches as a
+    // substring.
+    fn name_pattern(&self) -> Option<Result<Regex, regex::Error>> {
+        let name = self.target.name.as_ref()?;
+        if self.name_regex {
+            Some(Regex::new(name))
+        } else {
+            Some(Regex::new(&regex::escape(name)))
+        }
+    }
+}
+
+// An empty pattern would match every process.
+fn parse_pattern(text: &str) -> Result<String, String> {
+    if text.trim().is_empty() {
+        return Err(String::from("the pattern is empty"));
+    }
+    Ok(text.to_string())
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+#[value(rename_all = "UPPER")]
+pub enum KillSignal {
+    Term,
+    Kill,
+    Int,
+    Hup,
+    Stop,
+    Cont,
+    Usr1,
+    Usr2,
+}
+
+impl fmt::Display for KillSignal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => write!(f, "{}", value.get_name()),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl KillSignal {
+    pub fn to_signal(self) -> Signal {
+        match self {
+            KillSignal::Term => Signal::Term,
+            KillSignal::Kill => Signal::Kill,
+            KillSignal::Int => Signal::Interrupt,
+            KillSignal::Hup => Signal::Hangup,
+            KillSignal::Stop => Signal::Stop,
+            KillSignal::Cont => Signal::Continue,
+            KillSignal::Usr1 => Signal::User1,
+            KillSignal::Usr2 => Signal::User2,
+        }
+    }
+}
+
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+pub struct KillTarget {
+    /// Ports whose processes should be killed, e.g. `3000 3001`, `3000,3001` or `3000-3005`
+    #[arg(value_parser = parse_port_range, value_delimiter = ',')]
+    pub ports: Vec<PortRange>,
+
+    /// Kill this pid directly
+    #[arg(long)]
+    pub pid: Option<u32>,
+
+    /// Kill every process holding a socket whose name contains this text
+    #[arg(long, value_parser = parse_pattern)]
+    pub name: Option<String>,
+}
+
+impl KillTarget {
+    // Every port named by the arguments, in order and without repeats.
+    pub fn port_list(&self) -> Vec<u16> {
+        let mut ports: Vec<u16> = self.ports.iter().flat_map(|range| range.ports()).collect();
+        ports.sort();
+        ports.dedup();
+        ports
+    }
+}
+
+impl fmt::Display for KillTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let [range] = self.ports.as_slice()
+            && range.start == range.end
+        {
+            write!(f, "port {}", range)
+        } else if !self.ports.is_empty() {
+            let ranges: Vec<String> = self.ports.iter().map(|range| range.to_string()).collect();
+            write!(f, "ports {}", ranges.join(", "))
+        } else if let Some(pid) = self.pid {
+            write!(f, "pid {}", pid)
+        } else if let Some(name) = &self.name {
+            write!(f, "processes named {:?}", name)
+        } else {
+            write!(f, "nothing")
+        }
+    }
+}
+
+// A single port or an inclusive range like 3000-3005.
+#[derive(Debug, Clone, Copy)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn ports(self) -> RangeInclusive<u16> {
+        self.start..=self.end
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+fn parse_port_range(text: &str) -> Result<PortRange, String> {
+    let parse = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .map_err(|_| format!("{:?} is not a port number", port))
+    };
+    let range = match text.split_once('-') {
+        Some((start, end)) => PortRange {
+            start: parse(start)?,
+            end: parse(end)?,
+        },
+        None => {
+            let port = parse(text)?;
+            PortRange {
+                start: port,
+                end: port,
+            }
+        }
+    };
+    if range.start > range.end {
+        return Err(format!("the range {} runs backwards", text));
+    }
+    Ok(range)
+}
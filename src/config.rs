@@ -0,0 +1,81 @@
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use plug::ProtocolInfo;
+use plug::SortKey;
+use plug::cli::Filters;
+
+// Defaults read from `config.toml` in the platform config directory
+// (~/.config/plug on Linux). Flags given on the command line win over these.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub protocol: Option<ProtocolInfo>,
+    pub listening: bool,
+    pub sort: Option<SortKey>,
+    pub color: Option<bool>,
+    pub confirm_kill: bool,
+    // process names that need --allow-protected to kill, e.g. ["postgres"]
+    pub protected: Vec<String>,
+    // `false` stops recording sent signals in history.log
+    pub history: Option<bool>,
+    // TOML keys are always strings, see `port_labels` for the parsed form
+    pub labels: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "plug").map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    // A missing file just means the defaults, a broken one is reported and
+    // ignored so a typo never locks the user out of the tool.
+    pub fn load() -> Config {
+        let Some(path) = Config::path() else {
+            return Config::default();
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Config::default(),
+            Err(err) => {
+                eprintln!("could not read config {}: {}", path.display(), err);
+                return Config::default();
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("ignoring invalid config {}: {}", path.display(), err);
+                Config::default()
+            }
+        }
+    }
+
+    // Labels on anything that isn't a port 1-65535 are reported and dropped.
+    pub fn port_labels(&self) -> HashMap<u16, String> {
+        let mut labels = HashMap::new();
+        for (port, label) in &self.labels {
+            match port.parse::<u16>() {
+                Ok(port) if port != 0 => {
+                    labels.insert(port, label.clone());
+                }
+                _ => eprintln!(
+                    "ignoring label {:?} for {:?}: ports range from 1 to 65535",
+                    label, port
+                ),
+            }
+        }
+        labels
+    }
+
+    pub fn fill_filters(&self, filters: &mut Filters) {
+        filters.protocol = filters.protocol.or(self.protocol);
+        filters.listening |= self.listening;
+    }
+}
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+
+// The Docker API answers from memory, anything slower than this means the
+// daemon is wedged and the list shouldn't wait for it.
+const DOCKER_TIMEOUT: Duration = Duration::from_millis(300);
+
+// Prefixes container runtimes put in front of the ID in cgroup paths, e.g.
+// `/system.slice/docker-<id>.scope`.
+const RUNTIME_PREFIXES: &[&str] = &["docker-", "cri-containerd-", "crio-", "libpod-"];
+
+#[derive(Debug, Clone)]
+pub struct Container {
+    pub id: String,
+    pub name: Option<String>,
+    pub image: Option<String>,
+}
+
+impl Container {
+    pub fn short_id(&self) -> &str {
+        &self.id[..12]
+    }
+
+    // "docker: my-app-db-1", or the short ID when Docker couldn't name it
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => format!("docker: {}", name),
+            None => format!("container: {}", self.short_id()),
+        }
+    }
+}
+
+// The 64-hex-digit container ID from /proc/<pid>/cgroup, if the process runs
+// in a container. Covers Docker, containerd, CRI-O and Podman layouts on both
+// cgroup v1 and v2.
+pub fn container_id(pid: u32) -> Option<String> {
+    let cgroup = fs::read_to_string(format!("/proc/{pid}/cgroup")).ok()?;
+    cgroup
+        .lines()
+        .filter_map(|line| line.rsplit(':').next())
+        .flat_map(|path| path.split('/'))
+        .filter_map(|segment| {
+            let segment = segment.strip_suffix(".scope").unwrap_or(segment);
+            let segment = RUNTIME_PREFIXES
+                .iter()
+                .find_map(|prefix| segment.strip_prefix(prefix))
+                .unwrap_or(segment);
+            let is_id = segment.len() == 64 && segment.bytes().all(|b| b.is_ascii_hexdigit());
+            is_id.then(|| segment.to_string())
+        })
+        .next_back()
+}
+
+// Asks the Docker daemon for the container's name and image. Returns the bare
+// ID when there's no daemon to ask, e.g. for containerd or rootless setups.
+pub fn inspect(id: &str) -> Container {
+    let (name, image) = docker_inspect(id).unwrap_or_default();
+    Container {
+        id: id.to_string(),
+        name,
+        image,
+    }
+}
+
+fn docker_inspect(id: &str) -> Option<(Option<String>, Option<String>)> {
+    let mut stream = UnixStream::connect(DOCKER_SOCKET).ok()?;
+    stream.set_read_timeout(Some(DOCKER_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(DOCKER_TIMEOUT)).ok()?;
+
+    // HTTP/1.0 so the daemon closes the connection instead of chunking
+    let request = format!("GET /containers/{id}/json HTTP/1.0\r\nHost: docker\r\n\r\n");
+    stream.write_all(request.as_bytes()).ok()?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).ok()?;
+
+    let (head, body) = response.split_once("\r\n\r\n")?;
+    if !head.starts_with("HTTP/1.0 200") && !head.starts_with("HTTP/1.1 200") {
+        return None;
+    }
+    let details: serde_json::Value = serde_json::from_str(body).ok()?;
+    let name = details["Name"]
+        .as_str()
+        .map(|name| name.trim_start_matches('/').to_string());
+    let image = details["Config"]["Image"].as_str().map(str::to_string);
+    Some((name, image))
+}
@@ -0,0 +1,150 @@
+use chrono::{Local, SecondsFormat, TimeZone, Utc};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use sysinfo::{Process, ProcessStatus};
+
+#[cfg(unix)]
+use crate::priority;
+#[cfg(target_os = "linux")]
+use crate::procfs;
+use crate::{Manager, PortInfo, format_start_time, process_name, thread_count, zombie_note};
+
+// Everything "View Details" knows about one process, read once so the pretty
+// printer and `plug info --json` can't drift apart. Fields that couldn't be
+// read are null in the JSON rather than missing.
+#[derive(Debug, Serialize)]
+pub struct ProcessDetails {
+    pub pid: u32,
+    pub name: String,
+    pub user: Option<String>,
+    pub status: String,
+    pub memory_bytes: u64,
+    pub virtual_memory_bytes: u64,
+    pub shared_memory_bytes: Option<u64>, // Linux only
+    pub code_bytes: Option<u64>,          // Linux only
+    pub data_bytes: Option<u64>,          // Linux only, includes the stack
+    pub cpu_percent: f32,                 // of the whole machine, not one core
+    pub nice: Option<i32>,
+    pub threads: Option<usize>,
+    pub disk: Option<DiskUsage>,
+    pub run_time_secs: u64,
+    pub start_time: Option<String>, // RFC 3339
+    pub cmd: Vec<String>,
+    pub exe: Option<PathBuf>,
+    pub cwd: Option<PathBuf>,
+    pub parent_pid: Option<u32>,
+    pub sockets: Vec<PortInfo>,
+    #[serde(skip)]
+    pub start_time_label: String, // as the list shows it
+    #[serde(skip)]
+    pub zombie_note: Option<String>,
+    #[serde(skip)]
+    pub stale: bool, // run time and start time disagree
+}
+
+// The "recent" counters cover the CPU sampling window.
+#[derive(Debug, Serialize)]
+pub struct DiskUsage {
+    pub read_bytes: u64,
+    pub recent_read_bytes: u64,
+    pub written_bytes: u64,
+    pub recent_written_bytes: u64,
+}
+
+impl ProcessDetails {
+    // Expects `refresh_process_details` and `sample_cpu_usage` to have run for
+    // the process.
+    pub fn collect(manager: &Manager, process: &Process) -> ProcessDetails {
+        let pid = process.pid().as_u32();
+        let cores = manager.system_info.cpus().len().max(1);
+
+        #[cfg(target_os = "linux")]
+        let statm = procfs::statm(pid).ok();
+        #[cfg(target_os = "linux")]
+        let (shared, code, data) = (
+            statm.as_ref().map(|statm| statm.shared),
+            statm.as_ref().map(|statm| statm.text),
+            statm.as_ref().map(|statm| statm.data),
+        );
+        #[cfg(not(target_os = "linux"))]
+        let (shared, code, data) = (None, None, None);
+
+        #[cfg(unix)]
+        let nice = priority::get(pid).ok();
+        #[cfg(not(unix))]
+        let nice = None;
+
+        // run_time is measured at the last refresh, so a large gap to the
+        // wall clock means the numbers are stale
+        let since_start = Utc::now().timestamp() - process.start_time() as i64;
+        let stale =
+            process.start_time() != 0 && since_start.abs_diff(process.run_time() as i64) > 60;
+
+        ProcessDetails {
+            pid,
+            name: process_name(process.name()),
+            user: manager.user_name(process),
+            status: process.status().to_string(),
+            memory_bytes: process.memory(),
+            virtual_memory_bytes: process.virtual_memory(),
+            shared_memory_bytes: shared,
+            code_bytes: code,
+            data_bytes: data,
+            cpu_percent: process.cpu_usage() / cores as f32,
+            nice,
+            threads: thread_count(process),
+            disk: disk_usage(process),
+            run_time_secs: process.run_time(),
+            start_time: rfc3339(process.start_time(), manager.utc),
+            cmd: process
+                .cmd()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            exe: readable_path(process.exe()),
+            cwd: readable_path(process.cwd()),
+            parent_pid: process.parent().map(|parent| parent.as_u32()),
+            sockets: manager.ports_for_pid(pid).into_iter().cloned().collect(),
+            start_time_label: format_start_time(process.start_time(), &Local, manager.utc),
+            zombie_note: (process.status() == ProcessStatus::Zombie).then(|| zombie_note(process)),
+            stale,
+        }
+    }
+}
+
+// sysinfo reports zeroes rather than an error when /proc/<pid>/io is off limits.
+fn disk_usage(process: &Process) -> Option<DiskUsage> {
+    #[cfg(target_os = "linux")]
+    if !procfs::io_readable(process.pid().as_u32()) {
+        return None;
+    }
+
+    let usage = process.disk_usage();
+    Some(DiskUsage {
+        read_bytes: usage.total_read_bytes,
+        recent_read_bytes: usage.read_bytes,
+        written_bytes: usage.total_written_bytes,
+        recent_written_bytes: usage.written_bytes,
+    })
+}
+
+// sysinfo leaves paths empty when it isn't allowed to read them.
+fn readable_path(path: Option<&Path>) -> Option<PathBuf> {
+    path.filter(|path| !path.as_os_str().is_empty())
+        .map(Path::to_path_buf)
+}
+
+// Same rules as `format_start_time`: 0 and out-of-range values mean unknown.
+fn rfc3339(secs: u64, utc: bool) -> Option<String> {
+    if secs == 0 {
+        return None;
+    }
+    let start_time = Utc.timestamp_opt(i64::try_from(secs).ok()?, 0).single()?;
+    Some(if utc {
+        start_time.to_rfc3339_opts(SecondsFormat::Secs, true)
+    } else {
+        start_time
+            .with_timezone(&Local)
+            .to_rfc3339_opts(SecondsFormat::Secs, true)
+    })
+}
@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How long the details view waits for reverse lookups before it shows the
+// bare addresses instead.
+pub const LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// Reverse-resolves every address at once. Addresses without a name, or whose
+// lookup didn't finish within `timeout`, map to None; slow lookups keep
+// running in the background but nobody waits for them.
+pub fn reverse_lookup_all(addrs: &[IpAddr], timeout: Duration) -> HashMap<IpAddr, Option<String>> {
+    let (sender, receiver) = mpsc::channel();
+    for addr in addrs {
+        let addr = *addr;
+        let sender = sender.clone();
+        thread::spawn(move || {
+            let name = dns_lookup::lookup_addr(&addr).ok();
+            // the receiver is gone once the deadline passed
+            let _ = sender.send((addr, name));
+        });
+    }
+    drop(sender);
+
+    let mut names: HashMap<IpAddr, Option<String>> =
+        addrs.iter().map(|addr| (*addr, None)).collect();
+    let deadline = Instant::now() + timeout;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok((addr, name)) => {
+                names.insert(addr, name);
+            }
+            Err(_) => break,
+        }
+    }
+    names
+}
@@ -0,0 +1,30 @@
+use sysinfo::Process;
+
+// Variables whose names contain any of these are treated as secrets.
+const SECRET_MARKERS: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "KEY"];
+
+pub const MASK: &str = "********";
+
+// The process's environment as (key, value) pairs sorted by key. sysinfo hands
+// back an empty list when the environment can't be read, e.g. for processes
+// owned by other users.
+pub fn sorted_vars(process: &Process) -> Vec<(String, String)> {
+    let mut vars: Vec<(String, String)> = process
+        .environ()
+        .iter()
+        .map(|var| {
+            let var = var.to_string_lossy();
+            match var.split_once('=') {
+                Some((key, value)) => (key.to_string(), value.to_string()),
+                None => (var.to_string(), String::new()),
+            }
+        })
+        .collect();
+    vars.sort();
+    vars
+}
+
+pub fn is_secret(key: &str) -> bool {
+    let key = key.to_uppercase();
+    SECRET_MARKERS.iter().any(|marker| key.contains(marker))
+}
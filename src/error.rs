@@ -0,0 +1,44 @@
+use std::io::{self, ErrorKind};
+use thiserror::Error;
+
+// Why a scan failed, worded for whoever ran plug. The common causes say
+// what to do about them.
+#[derive(Debug, Error)]
+pub enum PlugError {
+    #[error("not allowed to read the socket table ({0}), try running with sudo")]
+    PermissionDenied(io::Error),
+    #[error(
+        "this system doesn't let plug read its sockets ({0}), some containers and sandboxes block it"
+    )]
+    Unsupported(io::Error),
+    #[error("could not read the socket table: {0}")]
+    Sockets(io::Error),
+}
+
+impl From<netstat2::error::Error> for PlugError {
+    fn from(err: netstat2::error::Error) -> PlugError {
+        use netstat2::error::Error;
+
+        let err = match err {
+            Error::OsError(err)
+            | Error::FailedToListProcesses(err)
+            | Error::FailedToQueryFileDescriptors(err) => err,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Error::NetLinkPacketError(message) => message.to_io(),
+            err => io::Error::other(err),
+        };
+        match err.kind() {
+            ErrorKind::PermissionDenied => PlugError::PermissionDenied(err),
+            ErrorKind::Unsupported => PlugError::Unsupported(err),
+            #[cfg(unix)]
+            _ if matches!(
+                err.raw_os_error(),
+                Some(libc::EAFNOSUPPORT | libc::EPROTONOSUPPORT)
+            ) =>
+            {
+                PlugError::Unsupported(err)
+            }
+            _ => PlugError::Sockets(err),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+// A process's descriptors from /proc/<pid>/fd, grouped by what they point at.
+#[derive(Default)]
+pub struct OpenFiles {
+    pub files: Vec<(u32, PathBuf)>,
+    pub sockets: HashMap<u32, u32>, // socket inode -> fd
+    pub pipes: usize,
+    pub other: usize,
+}
+
+impl OpenFiles {
+    pub fn total(&self) -> usize {
+        self.files.len() + self.sockets.len() + self.pipes + self.other
+    }
+}
+
+pub fn read(pid: u32) -> io::Result<OpenFiles> {
+    let mut open_files = OpenFiles::default();
+
+    for entry in fs::read_dir(format!("/proc/{pid}/fd"))? {
+        let entry = entry?;
+        let Some(fd) = entry.file_name().to_str().and_then(|fd| fd.parse().ok()) else {
+            continue;
+        };
+        // descriptors can be closed while we iterate
+        let Ok(target) = fs::read_link(entry.path()) else {
+            continue;
+        };
+
+        let target = target.to_string_lossy().into_owned();
+        if let Some(inode) = target
+            .strip_prefix("socket:[")
+            .and_then(|rest| rest.strip_suffix(']'))
+            .and_then(|inode| inode.parse().ok())
+        {
+            open_files.sockets.insert(inode, fd);
+        } else if target.starts_with("pipe:") {
+            open_files.pipes += 1;
+        } else if target.starts_with('/') {
+            open_files.files.push((fd, PathBuf::from(target)));
+        } else {
+            open_files.other += 1;
+        }
+    }
+
+    open_files.files.sort();
+    Ok(open_files)
+}
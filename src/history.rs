@@ -0,0 +1,94 @@
+use chrono::{Local, SecondsFormat};
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use sysinfo::{Process, Signal};
+
+use crate::process_name;
+
+// The first failed write of a run, kept for the caller to report.
+static WRITE_ERROR: OnceLock<String> = OnceLock::new();
+
+// One signal plug sent, stored as a JSON line in history.log so "what killed
+// my service at 14:32?" has an answer.
+#[derive(Serialize, Deserialize)]
+pub struct Entry {
+    pub timestamp: String, // RFC 3339, local time
+    pub signal: String,
+    pub pid: u32,
+    pub name: String,
+    pub ports: Vec<u16>,
+    pub cmd: Vec<String>,
+    pub outcome: String, // e.g. "terminated" or "failed"
+}
+
+impl Entry {
+    pub fn new(process: &Process, ports: Vec<u16>, signal: Signal, outcome: &str) -> Entry {
+        Entry {
+            timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            signal: signal.to_string(),
+            pid: process.pid().as_u32(),
+            name: process_name(process.name()),
+            ports,
+            cmd: process
+                .cmd()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            outcome: outcome.to_string(),
+        }
+    }
+}
+
+// ~/.local/share/plug/history.log on Linux.
+pub fn path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "plug").map(|dirs| dirs.data_dir().join("history.log"))
+}
+
+// The signal has already been sent by the time this runs, so a log that
+// can't be written doesn't fail anything, see `write_error`.
+pub fn record(entry: &Entry) {
+    let Some(path) = path() else {
+        return;
+    };
+    let written = serde_json::to_string(entry)
+        .map_err(io::Error::other)
+        .and_then(|line| {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+            writeln!(file, "{}", line)
+        });
+    if let Err(err) = written {
+        let _ = WRITE_ERROR.set(format!("could not write {}: {}", path.display(), err));
+    }
+}
+
+// Why recording failed, if it did at any point in this run.
+pub fn write_error() -> Option<&'static str> {
+    WRITE_ERROR.get().map(String::as_str)
+}
+
+// The last `count` entries, oldest first. Lines that don't parse, e.g. from a
+// write cut short, are skipped.
+pub fn last(count: usize) -> io::Result<Vec<Entry>> {
+    let Some(path) = path() else {
+        return Ok(vec![]);
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let entries: Vec<Entry> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = entries.len().saturating_sub(count);
+    Ok(entries.into_iter().skip(skip).collect())
+}
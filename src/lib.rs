@@ -0,0 +1,2270 @@
+// The scanning and killing behind the plug binary. Nothing in here prints,
+// results come back as data for the caller to show.
+
+use chrono::{Local, LocalResult, TimeZone, Utc};
+use clap::ValueEnum;
+use core::fmt;
+use netstat2::{AddressFamilyFlags, ProtocolFlags, TcpState};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::{OsStr, OsString};
+use std::mem;
+use std::net::{IpAddr, SocketAddr};
+use std::num::NonZeroUsize;
+use std::process;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use sysinfo::{
+    CpuRefreshKind, MINIMUM_CPU_UPDATE_INTERVAL, Pid, Process, ProcessRefreshKind, ProcessStatus,
+    ProcessesToUpdate, Signal, System, UpdateKind, Users, get_current_pid,
+};
+
+use cli::Filters;
+use relaunch::{LaunchSpec, wait_for_exit};
+use services::ServiceNames;
+use sockets::{Netstat, SocketRecord, SocketSource};
+
+pub mod cli;
+#[cfg(target_os = "linux")]
+pub mod container;
+pub mod details;
+mod dns;
+pub mod environ;
+pub mod error;
+#[cfg(target_os = "linux")]
+pub mod fds;
+pub mod history;
+pub mod output;
+#[cfg(unix)]
+pub mod priority;
+#[cfg(target_os = "linux")]
+mod procfs;
+pub mod relaunch;
+pub mod services;
+pub mod snapshot;
+pub mod sockets;
+#[cfg(windows)]
+mod winsvc;
+
+pub use details::ProcessDetails;
+pub use error::PlugError;
+
+// How long a killed process gets to disappear before it's reported as still
+// running.
+const KILL_CHECK_PERIOD: Duration = Duration::from_secs(2);
+
+// Exit codes for `plug kill` when a signal was delivered but the process or
+// its port outlived it, and when a signal couldn't be delivered at all.
+pub const EXIT_STILL_RUNNING: i32 = 3;
+pub const EXIT_KILL_FAILED: i32 = 4;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProtocolInfo {
+    TCP,
+    UDP,
+}
+
+impl fmt::Display for ProtocolInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProtocolInfo::TCP => write!(f, "tcp"),
+            ProtocolInfo::UDP => write!(f, "udp"),
+        }
+    }
+}
+
+/// Either case, so "tcp" as printed and "TCP" as typed both parse.
+///
+/// ```
+/// use plug::ProtocolInfo;
+///
+/// assert_eq!("tcp".parse(), Ok(ProtocolInfo::TCP));
+/// assert_eq!(ProtocolInfo::UDP.to_string(), "udp");
+/// assert_eq!(ProtocolInfo::UDP.to_string().parse(), Ok(ProtocolInfo::UDP));
+/// assert!("sctp".parse::<ProtocolInfo>().is_err());
+/// ```
+impl FromStr for ProtocolInfo {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<ProtocolInfo, String> {
+        match text.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(ProtocolInfo::TCP),
+            "udp" => Ok(ProtocolInfo::UDP),
+            _ => Err(format!("unknown protocol {:?}, expected tcp or udp", text)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProtocolInfo {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<ProtocolInfo, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+// The columns `--sort` can order entries by.
+#[derive(Debug, Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    Port,
+    Pid,
+    Name,
+    Protocol,
+    /// Longest-running processes first
+    #[value(alias = "uptime")]
+    Age,
+}
+
+// The TCP state of a socket, UDP sockets have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SocketState {
+    Closed,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait1,
+    FinWait2,
+    CloseWait,
+    Closing,
+    LastAck,
+    TimeWait,
+    DeleteTcb,
+    Unknown,
+    Stateless,
+}
+
+impl From<TcpState> for SocketState {
+    fn from(state: TcpState) -> SocketState {
+        match state {
+            TcpState::Closed => SocketState::Closed,
+            TcpState::Listen => SocketState::Listen,
+            TcpState::SynSent => SocketState::SynSent,
+            TcpState::SynReceived => SocketState::SynReceived,
+            TcpState::Established => SocketState::Established,
+            TcpState::FinWait1 => SocketState::FinWait1,
+            TcpState::FinWait2 => SocketState::FinWait2,
+            TcpState::CloseWait => SocketState::CloseWait,
+            TcpState::Closing => SocketState::Closing,
+            TcpState::LastAck => SocketState::LastAck,
+            TcpState::TimeWait => SocketState::TimeWait,
+            TcpState::DeleteTcb => SocketState::DeleteTcb,
+            TcpState::Unknown => SocketState::Unknown,
+        }
+    }
+}
+
+// Spelled the way netstat does, "N/A" for UDP.
+impl fmt::Display for SocketState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            SocketState::Closed => "CLOSED",
+            SocketState::Listen => "LISTEN",
+            SocketState::SynSent => "SYN_SENT",
+            SocketState::SynReceived => "SYN_RCVD",
+            SocketState::Established => "ESTABLISHED",
+            SocketState::FinWait1 => "FIN_WAIT_1",
+            SocketState::FinWait2 => "FIN_WAIT_2",
+            SocketState::CloseWait => "CLOSE_WAIT",
+            SocketState::Closing => "CLOSING",
+            SocketState::LastAck => "LAST_ACK",
+            SocketState::TimeWait => "TIME_WAIT",
+            SocketState::DeleteTcb => "DELETE_TCB",
+            SocketState::Unknown => "__UNKNOWN",
+            SocketState::Stateless => "N/A",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Names of processes whose death usually takes the machine, the desktop or
+// the remote session down with it. The config file can add more.
+#[cfg(target_os = "linux")]
+const PROTECTED_PROCESS_NAMES: &[&str] = &[
+    "init",
+    "systemd",
+    "systemd-journald",
+    "systemd-logind",
+    "dbus-daemon",
+    "sshd",
+];
+#[cfg(target_os = "macos")]
+const PROTECTED_PROCESS_NAMES: &[&str] = &[
+    "launchd",
+    "kernel_task",
+    "WindowServer",
+    "loginwindow",
+    "sshd",
+];
+#[cfg(windows)]
+const PROTECTED_PROCESS_NAMES: &[&str] = &[
+    "wininit.exe",
+    "winlogon.exe",
+    "csrss.exe",
+    "smss.exe",
+    "services.exe",
+    "lsass.exe",
+    "svchost.exe",
+    "explorer.exe",
+];
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+const PROTECTED_PROCESS_NAMES: &[&str] = &["init", "sshd"];
+
+// What happened to one process on the receiving end of a kill. The kill
+// functions only collect these, printing is up to the caller.
+#[derive(Debug, Clone)]
+pub struct KillOutcome {
+    pub pid: u32,
+    pub name: String,
+    pub signal: Signal,
+    pub status: KillStatus,
+    pub note: Option<String>, // e.g. a zombie that won't go away
+    // how to start it again, only for kills that ended it
+    pub relaunch: Option<Result<LaunchSpec, String>>,
+}
+
+// What a kill is aimed at and how. Only one of `ports`, `pid` and `name` is
+// expected to be set, the first one that is picks the targets.
+#[derive(Debug, Clone)]
+pub struct KillRequest {
+    pub ports: Vec<u16>,
+    pub pid: Option<u32>,
+    pub name: Option<Regex>,
+    pub older_than: Option<u64>, // seconds, younger processes are spared
+    pub signal: Signal,
+    pub tree: bool,
+    pub allow_protected: bool,
+    pub dry_run: bool,
+}
+
+// Nothing targeted yet, SIGKILL once something is.
+impl Default for KillRequest {
+    fn default() -> KillRequest {
+        KillRequest {
+            ports: vec![],
+            pid: None,
+            name: None,
+            older_than: None,
+            signal: Signal::Kill,
+            tree: false,
+            allow_protected: false,
+            dry_run: false,
+        }
+    }
+}
+
+// What became of a pid since the last scan, see `Manager::check_pid`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PidCheck {
+    Unchanged,
+    Gone,
+    Reused(String), // what holds the pid now, e.g. "now: sleep, started 3s ago"
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KillStatus {
+    AlreadyGone,  // exited before the signal was sent
+    Failed,       // the signal couldn't be delivered
+    Sent,         // delivered, and not a signal that ends the process
+    Terminated,   // gone within KILL_CHECK_PERIOD
+    StillRunning, // delivered but outlived KILL_CHECK_PERIOD
+    DryRun,       // nothing was sent
+    Reused,       // the pid belongs to another process since the scan, nothing was sent
+    Protected,    // refused without --allow-protected, nothing was sent
+}
+
+impl KillStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            KillStatus::AlreadyGone => "already gone",
+            KillStatus::Failed => "failed",
+            KillStatus::Sent => "signalled",
+            KillStatus::Terminated => "terminated",
+            KillStatus::StillRunning => "still running",
+            KillStatus::DryRun => "would kill",
+            KillStatus::Reused => "pid reused",
+            KillStatus::Protected => "protected",
+        }
+    }
+}
+
+impl KillOutcome {
+    pub fn gone(pid: u32, signal: Signal) -> KillOutcome {
+        KillOutcome {
+            pid,
+            name: String::from("(unknown)"),
+            signal,
+            status: KillStatus::AlreadyGone,
+            note: None,
+            relaunch: None,
+        }
+    }
+}
+
+impl fmt::Display for KillOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.status {
+            KillStatus::AlreadyGone => write!(f, "pid {} already exited", self.pid),
+            KillStatus::Failed => write!(
+                f,
+                "failed to send {} to {} (pid {}), processes of other users need sudo",
+                self.signal, self.name, self.pid
+            ),
+            KillStatus::Sent => write!(
+                f,
+                "sent {} to {} (pid {})",
+                self.signal, self.name, self.pid
+            ),
+            KillStatus::DryRun => write!(
+                f,
+                "would send {} to {} (pid {})",
+                self.signal, self.name, self.pid
+            ),
+            KillStatus::Terminated => {
+                write!(f, "terminated {} (pid {})", self.name, self.pid)
+            }
+            // the note says what holds the pid now
+            KillStatus::Reused => write!(
+                f,
+                "pid {} is no longer {} ({}), nothing was sent",
+                self.pid,
+                self.name,
+                self.note.as_deref().unwrap_or("now: unknown")
+            ),
+            // a descendant of the tree that was asked for
+            KillStatus::Protected if let Some(note) = &self.note => write!(
+                f,
+                "skipped protected process {} (pid {}) {}",
+                self.name, self.pid, note
+            ),
+            KillStatus::Protected => write!(
+                f,
+                "refusing to kill protected process {} (pid {}), pass --allow-protected to override",
+                self.name, self.pid
+            ),
+            // SIGKILL can't be caught, a survivor is stuck in the kernel
+            KillStatus::StillRunning if self.signal == Signal::Kill => write!(
+                f,
+                "sent {} to {} (pid {}) but it is still running, it may be blocked on I/O",
+                self.signal, self.name, self.pid
+            ),
+            KillStatus::StillRunning => write!(
+                f,
+                "sent {} to {} (pid {}) but it is still running (try --signal KILL)",
+                self.signal, self.name, self.pid
+            ),
+        }
+    }
+}
+
+// A failure anywhere decides the exit code over a process that lingers.
+pub fn kill_exit_code(outcomes: &[KillOutcome]) -> i32 {
+    if outcomes
+        .iter()
+        .any(|outcome| matches!(outcome.status, KillStatus::Failed | KillStatus::Reused))
+    {
+        EXIT_KILL_FAILED
+    } else if outcomes
+        .iter()
+        .any(|outcome| outcome.status == KillStatus::StillRunning)
+    {
+        EXIT_STILL_RUNNING
+    } else {
+        0
+    }
+}
+
+// "3 killed, 1 already gone, 0 failed", plus the processes that got a signal
+// like STOP or outlived theirs.
+pub fn kill_summary(outcomes: &[KillOutcome]) -> String {
+    let count = |wanted: &[KillStatus]| {
+        outcomes
+            .iter()
+            .filter(|outcome| wanted.contains(&outcome.status))
+            .count()
+    };
+    let mut summary = format!(
+        "{} killed, {} already gone, {} failed",
+        count(&[KillStatus::Terminated]),
+        count(&[KillStatus::AlreadyGone]),
+        count(&[KillStatus::Failed])
+    );
+    let signalled = count(&[KillStatus::Sent]);
+    if signalled > 0 {
+        summary.push_str(&format!(", {} signalled", signalled));
+    }
+    let lingering = count(&[KillStatus::StillRunning]);
+    if lingering > 0 {
+        summary.push_str(&format!(", {} still running", lingering));
+    }
+    let reused = count(&[KillStatus::Reused]);
+    if reused > 0 {
+        summary.push_str(&format!(", {} pids reused", reused));
+    }
+    let protected = count(&[KillStatus::Protected]);
+    if protected > 0 {
+        summary.push_str(&format!(", {} protected skipped", protected));
+    }
+    summary
+}
+
+// `pid` followed by its parent, grandparent and so on up to init, as far as
+// `processes` has them. A reused pid can make the parent links loop, the walk
+// stops at the first repeat.
+fn lineage(processes: &HashMap<Pid, Process>, pid: Pid) -> Vec<&Process> {
+    let mut chain: Vec<&Process> = vec![];
+    let mut seen = HashSet::new();
+    let mut current = processes.get(&pid);
+    while let Some(process) = current {
+        if !seen.insert(process.pid()) {
+            break;
+        }
+        chain.push(process);
+        current = process.parent().and_then(|parent| processes.get(&parent));
+    }
+    chain
+}
+
+// The processes a kill was meant to end, whether or not they did.
+pub fn killed_pids(outcomes: &[KillOutcome]) -> HashSet<u32> {
+    outcomes
+        .iter()
+        .filter(|outcome| {
+            matches!(
+                outcome.status,
+                KillStatus::Terminated | KillStatus::StillRunning
+            )
+        })
+        .map(|outcome| outcome.pid)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct PortInfo {
+    #[serde(rename = "port")]
+    pub port_number: u16,
+    pub pid: u32,
+    #[serde(rename = "name")]
+    pub process_name: String,
+    pub protocol: ProtocolInfo,
+    #[serde(rename = "status", serialize_with = "serialize_display")]
+    pub port_status: SocketState,
+    pub local_addr: IpAddr,
+    pub remote: Option<SocketAddr>, // the peer of a connected TCP socket
+    pub start_time: String,
+    #[serde(skip)]
+    pub run_time: u64, // seconds, as of the scan
+    #[serde(serialize_with = "serialize_display")]
+    pub process_status: ProcessStatus,
+    #[serde(skip)]
+    pub inode: Option<u32>,
+    pub socket_pids: Vec<u32>, // every pid sharing this socket, including `pid`
+    pub user: String,
+    #[serde(skip)]
+    pub other_user: bool,
+    pub service: Option<String>,
+    pub label: Option<String>,
+    pub container: Option<String>,       // e.g. "docker: my-app-db-1"
+    pub hosted_services: Option<String>, // what a Windows svchost.exe runs, e.g. "RpcSs, RpcEptMapper"
+    pub protected: bool,                 // see `Manager::is_protected`
+}
+
+impl fmt::Display for PortInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{} -- {}",
+            self.pid,
+            self.port_label(),
+            self.process_name
+        )?;
+        if self.protected {
+            write!(f, " (protected)")?;
+        }
+        if let Some(container) = &self.container {
+            write!(f, " [{}]", container)?;
+        }
+        if let Some(services) = &self.hosted_services {
+            write!(f, " [{}]", services)?;
+        }
+        write!(
+            f,
+            " Status: {} -- Protocol: {}",
+            self.port_status, self.protocol
+        )?;
+        if self.process_status == ProcessStatus::Stop {
+            write!(f, " -- Process: {}", self.process_status)?;
+        }
+        write!(f, " -- User: {}", self.user)?;
+        if self.other_user {
+            write!(f, " (other user)")?;
+        }
+        Ok(())
+    }
+}
+
+impl PortInfo {
+    // What makes two entries the same socket holder: the state, addresses and
+    // everything read from the process can change between two scans.
+    pub fn key(&self) -> (u32, u16, ProtocolInfo) {
+        (self.pid, self.port_number, self.protocol)
+    }
+
+    // Numbers match the port exactly, anything else is a case-insensitive
+    // substring of the process name. A blank query matches everything.
+    pub fn matches_query(&self, query: &str) -> bool {
+        let query = query.trim();
+        if query.is_empty() {
+            return true;
+        }
+        match query.parse::<u16>() {
+            Ok(port) => self.port_number == port,
+            Err(_) => self
+                .process_name
+                .to_lowercase()
+                .contains(&query.to_lowercase()),
+        }
+    }
+
+    // "443 (https)" when the port has a user label or a well-known service name
+    pub fn port_label(&self) -> String {
+        match self.label.as_ref().or(self.service.as_ref()) {
+            Some(service) => format!("{} ({})", self.port_number, service),
+            None => self.port_number.to_string(),
+        }
+    }
+
+    pub fn is_listening(&self) -> bool {
+        matches!(self.protocol, ProtocolInfo::TCP) && self.port_status == SocketState::Listen
+    }
+
+    // "127.0.0.1:8080 (loopback only)", the difference between a dev server
+    // and something the whole network can reach.
+    pub fn reachability(&self) -> String {
+        let addr = SocketAddr::new(self.local_addr, self.port_number);
+        if self.local_addr.is_loopback() {
+            format!("{} (loopback only)", addr)
+        } else if self.local_addr.is_unspecified() {
+            format!("{} (all interfaces)", addr)
+        } else {
+            addr.to_string()
+        }
+    }
+
+    // Wildcard binds are reachable through localhost; anything else has to be
+    // addressed the way it was bound or the connection will be refused.
+    pub fn browser_url(&self) -> String {
+        let scheme = match self.port_number {
+            443 | 8443 => "https",
+            _ => "http",
+        };
+        let host = match self.local_addr {
+            addr if addr.is_unspecified() => String::from("localhost"),
+            IpAddr::V4(addr) => addr.to_string(),
+            IpAddr::V6(addr) => format!("[{addr}]"),
+        };
+
+        format!("{scheme}://{host}:{}", self.port_number)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn thread_count(process: &Process) -> Option<usize> {
+    procfs::thread_count(process.pid().as_u32()).ok()
+}
+
+// sysinfo's task list leaves out the main thread
+#[cfg(not(target_os = "linux"))]
+fn thread_count(process: &Process) -> Option<usize> {
+    process.tasks().map(|tasks| tasks.len() + 1)
+}
+
+// A zombie has already exited and only waits for its parent to collect its
+// exit status, signals can't make it go away.
+fn zombie_note(process: &Process) -> String {
+    match process.parent() {
+        Some(parent) => format!(
+            "this process is a zombie, killing it does nothing; its parent (pid {}) has to reap it or be killed",
+            parent
+        ),
+        None => String::from("this process is a zombie, killing it does nothing"),
+    }
+}
+
+// What `Manager::collect` scans and how it labels what it finds.
+pub struct CollectOptions {
+    pub address_families: AddressFamilyFlags,
+    pub protocols: ProtocolFlags,
+    pub filters: Filters,
+    pub services: Option<&'static ServiceNames>, // None leaves ports unnamed
+    pub labels: HashMap<u16, String>,
+    pub protected_names: Vec<String>,
+    pub utc: bool,
+}
+
+// Every socket of both protocols and address families, nothing filtered out.
+impl Default for CollectOptions {
+    fn default() -> CollectOptions {
+        CollectOptions {
+            address_families: AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+            protocols: ProtocolFlags::TCP | ProtocolFlags::UDP,
+            filters: Filters::default(),
+            services: None,
+            labels: HashMap::new(),
+            protected_names: vec![],
+            utc: false,
+        }
+    }
+}
+
+// TODO: ***********************************************************************
+// TODO: REALLY FLESH OUT THE PORT PART FIRST, MAKE IT AWESOME, THEN WORK ON
+// TODO: THE PROCESS PART OF THE PROGRAM
+// TODO: ***********************************************************************
+
+#[derive(Debug)]
+pub struct Manager {
+    // Port-part of the Manager
+    pub port_infos: Vec<PortInfo>,
+    by_port: HashMap<u16, Vec<usize>>,    // port -> socket indices
+    by_process: HashMap<u32, Vec<usize>>, // pid -> socket indices
+    pub system_info: System,
+    users: Users,
+    pub services: Option<&'static ServiceNames>, // None with --numeric
+    pub labels: HashMap<u16, String>,
+    pub peer_names: HashMap<IpAddr, Option<String>>, // reverse DNS, kept for the session
+    pub utc: bool,                                   // start times in UTC rather than local time
+    unattributed: usize, // sockets of the last scan whose owner couldn't be seen
+    pub protected_names: Vec<String>, // from the config, on top of PROTECTED_PROCESS_NAMES
+    own_lineage: HashSet<u32>, // plug and everything above it, up to init
+    // Name and start time of every process as of the last scan. sysinfo
+    // swaps in the new process when a refresh finds a reused pid, so signals
+    // check against these rather than `system_info`.
+    scanned: HashMap<u32, (OsString, u64)>,
+    pub log_kills: bool, // append every sent signal to the history log
+    #[cfg(target_os = "linux")]
+    pub containers: HashMap<String, container::Container>, // by container ID, kept for the session
+    source: Box<dyn SocketSource>,
+    filters: Filters,                     // what the last `refresh` kept
+    address_families: AddressFamilyFlags, // what `refresh` scans
+    protocols: ProtocolFlags,
+}
+// TODO: Process-part of the Manager
+// process_info: Vec<sysinfo::Process>,
+
+impl Manager {
+    pub fn new() -> Manager {
+        Manager {
+            port_infos: vec![],
+            by_port: HashMap::new(),
+            by_process: HashMap::new(),
+            system_info: System::new(),
+            users: Users::new(),
+            services: None,
+            labels: HashMap::new(),
+            peer_names: HashMap::new(),
+            utc: false,
+            unattributed: 0,
+            protected_names: vec![],
+            own_lineage: HashSet::new(),
+            scanned: HashMap::new(),
+            log_kills: false,
+            #[cfg(target_os = "linux")]
+            containers: HashMap::new(),
+            source: Box::new(Netstat),
+            filters: Filters::default(),
+            address_families: AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+            protocols: ProtocolFlags::TCP | ProtocolFlags::UDP,
+            // process_info: vec![],
+        }
+    }
+
+    // A Manager holding one scan of `source`, later `refresh` calls read the
+    // same source for the same protocols and address families.
+    pub fn collect(
+        source: impl SocketSource + 'static,
+        options: CollectOptions,
+    ) -> Result<Manager, PlugError> {
+        let mut manager = Manager {
+            source: Box::new(source),
+            services: options.services,
+            labels: options.labels,
+            protected_names: options.protected_names,
+            utc: options.utc,
+            address_families: options.address_families,
+            protocols: options.protocols,
+            ..Manager::new()
+        };
+        manager.refresh(&options.filters)?;
+        Ok(manager)
+    }
+
+    // Every socket on `port`, in scan order.
+    pub fn find_by_port(&self, port: u16) -> Vec<&PortInfo> {
+        self.lookup(self.by_port.get(&port))
+    }
+
+    /// Every socket held by `pid`, in scan order.
+    ///
+    /// ```
+    /// # use plug::sockets::SocketRecord;
+    /// # use plug::{CollectOptions, Manager, ProtocolInfo, SocketState};
+    /// # let me = std::process::id();
+    /// # let socket = SocketRecord {
+    /// #     local_addr: "127.0.0.1".parse().unwrap(),
+    /// #     local_port: 8080,
+    /// #     remote: None,
+    /// #     protocol: ProtocolInfo::TCP,
+    /// #     state: SocketState::Listen,
+    /// #     pids: vec![me],
+    /// #     inode: None,
+    /// # };
+    /// # let manager = Manager::collect(vec![socket], CollectOptions::default())?;
+    /// let held = manager.ports_for_pid(me);
+    /// assert_eq!(held.len(), 1);
+    /// assert_eq!(held[0].port_number, 8080);
+    /// # Ok::<(), plug::PlugError>(())
+    /// ```
+    pub fn ports_for_pid(&self, pid: u32) -> Vec<&PortInfo> {
+        self.lookup(self.by_process.get(&pid))
+    }
+
+    /// The processes holding `port`, sorted. Forked workers sharing a socket
+    /// are each listed once.
+    ///
+    /// ```
+    /// use plug::sockets::SocketRecord;
+    /// use plug::{CollectOptions, Manager, ProtocolInfo, SocketState};
+    ///
+    /// let me = std::process::id();
+    /// let socket = SocketRecord {
+    ///     local_addr: "127.0.0.1".parse().unwrap(),
+    ///     local_port: 8080,
+    ///     remote: None,
+    ///     protocol: ProtocolInfo::TCP,
+    ///     state: SocketState::Listen,
+    ///     pids: vec![me],
+    ///     inode: None,
+    /// };
+    /// let manager = Manager::collect(vec![socket], CollectOptions::default())?;
+    /// assert_eq!(manager.pids_on_port(8080), vec![me]);
+    /// assert!(manager.pids_on_port(8081).is_empty());
+    /// # Ok::<(), plug::PlugError>(())
+    /// ```
+    pub fn pids_on_port(&self, port: u16) -> Vec<u32> {
+        let mut pids: Vec<u32> = self
+            .find_by_port(port)
+            .iter()
+            .map(|info| info.pid)
+            .collect();
+        pids.sort();
+        pids.dedup();
+        pids
+    }
+
+    /// Entries for the interactive filter, see `PortInfo::matches_query`.
+    ///
+    /// ```
+    /// # use plug::sockets::SocketRecord;
+    /// # use plug::{CollectOptions, Manager, ProtocolInfo, SocketState};
+    /// # let me = std::process::id();
+    /// # let socket = SocketRecord {
+    /// #     local_addr: "127.0.0.1".parse().unwrap(),
+    /// #     local_port: 8080,
+    /// #     remote: None,
+    /// #     protocol: ProtocolInfo::TCP,
+    /// #     state: SocketState::Listen,
+    /// #     pids: vec![me],
+    /// #     inode: None,
+    /// # };
+    /// # let manager = Manager::collect(vec![socket], CollectOptions::default())?;
+    /// assert_eq!(manager.entries_matching("8080").len(), 1);
+    /// assert!(manager.entries_matching("8081").is_empty());
+    /// assert_eq!(manager.entries_matching("").len(), 1);
+    /// # Ok::<(), plug::PlugError>(())
+    /// ```
+    pub fn entries_matching(&self, query: &str) -> Vec<&PortInfo> {
+        self.port_infos
+            .iter()
+            .filter(|info| info.matches_query(query))
+            .collect()
+    }
+
+    /// The entry at `index` of the last scan, None past the end.
+    ///
+    /// ```
+    /// # use plug::sockets::SocketRecord;
+    /// # use plug::{CollectOptions, Manager, ProtocolInfo, SocketState};
+    /// # let me = std::process::id();
+    /// # let socket = SocketRecord {
+    /// #     local_addr: "127.0.0.1".parse().unwrap(),
+    /// #     local_port: 8080,
+    /// #     remote: None,
+    /// #     protocol: ProtocolInfo::TCP,
+    /// #     state: SocketState::Listen,
+    /// #     pids: vec![me],
+    /// #     inode: None,
+    /// # };
+    /// # let manager = Manager::collect(vec![socket], CollectOptions::default())?;
+    /// assert_eq!(manager.entry(0).map(|info| info.pid), Some(me));
+    /// assert!(manager.entry(1).is_none());
+    /// # Ok::<(), plug::PlugError>(())
+    /// ```
+    pub fn entry(&self, index: usize) -> Option<&PortInfo> {
+        self.port_infos.get(index)
+    }
+
+    // Ports that more than one process is listening on, e.g. an old server
+    // that never died fighting its replacement. Workers sharing one socket
+    // through a fork all report the same holders and are not a conflict, and
+    // TCP and UDP on the same port are independent.
+    pub fn port_conflicts(&self) -> Vec<(u16, ProtocolInfo, Vec<u32>)> {
+        let mut conflicts = vec![];
+        for (port, indexes) in &self.by_port {
+            for protocol in [ProtocolInfo::TCP, ProtocolInfo::UDP] {
+                let listeners: Vec<&PortInfo> = indexes
+                    .iter()
+                    .map(|index| &self.port_infos[*index])
+                    .filter(|info| info.protocol == protocol)
+                    .filter(|info| protocol == ProtocolInfo::UDP || info.is_listening())
+                    .collect();
+                let Some(first) = listeners.first() else {
+                    continue;
+                };
+                if listeners
+                    .iter()
+                    .all(|info| info.socket_pids == first.socket_pids)
+                {
+                    continue;
+                }
+
+                let mut pids: Vec<u32> = listeners.iter().map(|info| info.pid).collect();
+                pids.sort();
+                pids.dedup();
+                if pids.len() > 1 {
+                    conflicts.push((*port, protocol, pids));
+                }
+            }
+        }
+        conflicts.sort_by_key(|(port, protocol, _)| (*port, *protocol as u8));
+        conflicts
+    }
+
+    // PID 1, plug itself with the shell and terminal it runs in, and a short
+    // list of system daemons. Killing any of them needs an explicit override.
+    pub fn is_protected(&self, pid: u32, name: &str) -> bool {
+        pid == 1
+            || self.own_lineage.contains(&pid)
+            || PROTECTED_PROCESS_NAMES.contains(&name)
+            || self
+                .protected_names
+                .iter()
+                .any(|protected| protected == name)
+    }
+
+    pub fn privilege_hint(&self) -> Option<String> {
+        match self.unattributed {
+            0 => None,
+            1 => Some(String::from(
+                "1 socket could not be attributed to a process; try running with sudo",
+            )),
+            n => Some(format!(
+                "{} sockets could not be attributed to a process; try running with sudo",
+                n
+            )),
+        }
+    }
+
+    // How many sockets are in each state, per protocol. TCP comes first and
+    // the most common state first within each protocol.
+    pub fn socket_stats(&self) -> Vec<(ProtocolInfo, SocketState, usize)> {
+        let mut counts: HashMap<(ProtocolInfo, SocketState), usize> = HashMap::new();
+        for info in &self.port_infos {
+            *counts.entry((info.protocol, info.port_status)).or_default() += 1;
+        }
+
+        let mut stats: Vec<(ProtocolInfo, SocketState, usize)> = counts
+            .into_iter()
+            .map(|((protocol, status), count)| (protocol, status, count))
+            .collect();
+        stats.sort_by(|a, b| {
+            (a.0 as u8)
+                .cmp(&(b.0 as u8))
+                .then(b.2.cmp(&a.2))
+                .then(a.1.cmp(&b.1))
+        });
+        stats
+    }
+
+    // "42 ports across 17 processes (31 TCP, 11 UDP)"
+    pub fn summary(&self) -> String {
+        let tcp = self
+            .port_infos
+            .iter()
+            .filter(|info| info.protocol == ProtocolInfo::TCP)
+            .count();
+        let udp = self.port_infos.len() - tcp;
+        let processes = self.by_process.len();
+
+        format!(
+            "{} {} across {} {} ({} TCP, {} UDP)",
+            self.port_infos.len(),
+            if self.port_infos.len() == 1 {
+                "port"
+            } else {
+                "ports"
+            },
+            processes,
+            if processes == 1 {
+                "process"
+            } else {
+                "processes"
+            },
+            tcp,
+            udp
+        )
+    }
+
+    fn lookup(&self, indexes: Option<&Vec<usize>>) -> Vec<&PortInfo> {
+        indexes.map_or(vec![], |indexes| {
+            indexes
+                .iter()
+                .filter_map(|index| self.entry(*index))
+                .collect()
+        })
+    }
+
+    // Rescans sockets and processes, keeping only the entries that pass
+    // `filters`. The indices in `by_port` and `by_process` point into the
+    // freshly built `port_infos`. When the socket table can't be read the
+    // previous scan is left as it was.
+    pub fn refresh(&mut self, filters: &Filters) -> Result<(), PlugError> {
+        let sockets = self.source.sockets(self.address_families, self.protocols)?;
+        self.system_info.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            list_refresh_kind(),
+        );
+        self.filters = filters.clone();
+        self.rebuild(&sockets);
+        Ok(())
+    }
+
+    // Like `refresh` with the filters of the last scan, but only the
+    // processes holding a socket are reread. Returns what changed.
+    pub fn rescan(&mut self) -> Result<RescanDiff, PlugError> {
+        let sockets = self.source.sockets(self.address_families, self.protocols)?;
+        let mut pids: Vec<Pid> = sockets
+            .iter()
+            .flat_map(|socket| socket.pids.iter())
+            .map(|pid| Pid::from_u32(*pid))
+            .chain(get_current_pid().ok())
+            .collect();
+        pids.sort();
+        pids.dedup();
+        self.system_info.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            list_refresh_kind(),
+        );
+
+        let before = mem::take(&mut self.port_infos);
+        self.rebuild(&sockets);
+        Ok(RescanDiff::between(&before, &self.port_infos))
+    }
+
+    // Builds `port_infos` and the indices over it from `sockets`, with the
+    // processes already refreshed.
+    fn rebuild(&mut self, sockets: &[SocketRecord]) {
+        self.port_infos.clear();
+        self.by_port.clear();
+        self.by_process.clear();
+        self.users.refresh();
+        // most of a busy table is TIME_WAIT without an owner
+        self.port_infos
+            .reserve(sockets.iter().map(|socket| socket.pids.len()).sum());
+
+        let proc = self.system_info.processes();
+        let mut i = 0;
+
+        self.own_lineage = get_current_pid()
+            .map(|pid| {
+                lineage(proc, pid)
+                    .iter()
+                    .map(|process| process.pid().as_u32())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        self.scanned = proc
+            .iter()
+            .map(|(pid, process)| {
+                (
+                    pid.as_u32(),
+                    (process.name().to_os_string(), process.start_time()),
+                )
+            })
+            .collect();
+
+        // entries owned by someone else are the ones a kill fails on without sudo
+        let current_uid = get_current_pid()
+            .ok()
+            .and_then(|pid| proc.get(&pid))
+            .and_then(|process| process.effective_user_id());
+
+        // Many sockets share a process, so look the timezone up once per
+        // refresh and format each process's start time and user only once.
+        let tz = Local::now().timezone();
+        let mut start_times: HashMap<u32, String> = HashMap::new();
+        let mut user_names: HashMap<u32, String> = HashMap::new();
+        // When plug itself runs in a container every process around it
+        // shares that cgroup, which says nothing about any of them.
+        #[cfg(target_os = "linux")]
+        let own_container = container::container_id(process::id());
+        #[cfg(target_os = "linux")]
+        let mut container_labels: HashMap<u32, Option<String>> = HashMap::new();
+        // svchost.exe alone says nothing, ask once per scan what each one runs
+        #[cfg(windows)]
+        let mut hosted: Option<HashMap<u32, String>> = None;
+
+        // Other users' sockets come back without pids unless plug can read
+        // their /proc entries. TIME_WAIT sockets belong to nobody anyway.
+        self.unattributed = sockets
+            .iter()
+            .filter(|socket| socket.pids.is_empty())
+            .filter(|socket| socket.state != SocketState::TimeWait)
+            .count();
+
+        for socket in sockets {
+            for &assoc_pid in &socket.pids {
+                let process = match proc.get(&Pid::from_u32(assoc_pid)) {
+                    Some(p) => p,
+                    None => continue,
+                };
+
+                #[cfg(target_os = "linux")]
+                let container = container_labels
+                    .entry(assoc_pid)
+                    .or_insert_with(|| {
+                        let id = container::container_id(assoc_pid)
+                            .filter(|id| Some(id) != own_container.as_ref())?;
+                        let container = self
+                            .containers
+                            .entry(id.clone())
+                            .or_insert_with(|| container::inspect(&id));
+                        Some(container.label())
+                    })
+                    .clone();
+                #[cfg(not(target_os = "linux"))]
+                let container = None;
+
+                #[cfg(windows)]
+                let hosted_services = process_name(process.name())
+                    .eq_ignore_ascii_case("svchost.exe")
+                    .then(|| {
+                        hosted
+                            .get_or_insert_with(winsvc::hosted_services)
+                            .get(&assoc_pid)
+                            .cloned()
+                    })
+                    .flatten();
+                #[cfg(not(windows))]
+                let hosted_services = None;
+
+                let mut port_info = PortInfo {
+                    port_number: socket.local_port,
+                    pid: assoc_pid,
+                    process_name: process_name(process.name()),
+                    protocol: socket.protocol,
+                    port_status: socket.state,
+                    local_addr: socket.local_addr,
+                    remote: socket.remote,
+                    start_time: start_times
+                        .entry(assoc_pid)
+                        .or_insert_with(|| format_start_time(process.start_time(), &tz, self.utc))
+                        .clone(),
+                    run_time: process.run_time(),
+                    process_status: process.status(),
+                    inode: socket.inode,
+                    socket_pids: socket.pids.clone(),
+                    user: user_names
+                        .entry(assoc_pid)
+                        .or_insert_with(|| {
+                            self.user_name(process).unwrap_or_else(|| String::from("?"))
+                        })
+                        .clone(),
+                    other_user: process.effective_user_id() != current_uid,
+                    service: self
+                        .services
+                        .and_then(|services| services.get(&(socket.local_port, socket.protocol)))
+                        .cloned(),
+                    label: self.labels.get(&socket.local_port).cloned(),
+                    container,
+                    hosted_services,
+                    protected: false,
+                };
+                port_info.protected = self.is_protected(assoc_pid, &port_info.process_name);
+
+                if !self.filters.matches(&port_info) {
+                    continue;
+                }
+
+                self.port_infos.push(port_info);
+
+                match self.by_process.get_mut(&assoc_pid) {
+                    Some(p_ind) => p_ind.push(i),
+                    None => {
+                        self.by_process.insert(assoc_pid, vec![i]);
+                    }
+                }
+
+                match self.by_port.get_mut(&socket.local_port) {
+                    Some(l_ind) => l_ind.push(i),
+                    None => {
+                        self.by_port.insert(socket.local_port, vec![i]);
+                    }
+                }
+
+                i += 1;
+            }
+        }
+    }
+
+    // Falls back to the numeric uid for users missing from the user database.
+    pub fn user_name(&self, process: &Process) -> Option<String> {
+        let uid = process.effective_user_id()?;
+        Some(match self.users.get_user_by_id(uid) {
+            Some(user) => user.name().to_string(),
+            None => uid.to_string(),
+        })
+    }
+
+    pub fn refresh_process_details(&mut self, processes: ProcessesToUpdate) {
+        self.system_info.refresh_processes_specifics(
+            processes,
+            false,
+            ProcessRefreshKind::everything(),
+        );
+    }
+
+    // CPU usage is the difference between two refreshes, so a single refresh
+    // always reads 0%. Only worth the wait when the number is shown. The core
+    // list is loaded too, sysinfo reports usage as a share of one core. Disk
+    // counters ride along so their deltas cover the same window.
+    pub fn sample_cpu_usage(&mut self, pids: &[u32]) {
+        let pids: Vec<Pid> = pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+        let cpu = ProcessRefreshKind::nothing().with_cpu().with_disk_usage();
+
+        if self.system_info.cpus().is_empty() {
+            self.system_info.refresh_cpu_list(CpuRefreshKind::nothing());
+        }
+
+        self.system_info
+            .refresh_processes_specifics(ProcessesToUpdate::Some(&pids), false, cpu);
+        thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+        self.system_info
+            .refresh_processes_specifics(ProcessesToUpdate::Some(&pids), false, cpu);
+    }
+
+    // Looks up the names of the peers `pid` is connected to, skipping the
+    // ones already tried this session.
+    pub fn resolve_peers(&mut self, pid: u32) {
+        let mut addrs: Vec<IpAddr> = self
+            .ports_for_pid(pid)
+            .iter()
+            .filter_map(|info| info.remote)
+            .map(|remote| remote.ip())
+            .filter(|ip| !self.peer_names.contains_key(ip))
+            .collect();
+        addrs.sort();
+        addrs.dedup();
+        if addrs.is_empty() {
+            return;
+        }
+
+        self.peer_names
+            .extend(dns::reverse_lookup_all(&addrs, dns::LOOKUP_TIMEOUT));
+    }
+
+    // Whether `pid` is still the process the last scan saw. The scan may be
+    // minutes old by now, long enough for the pid to be handed to an
+    // unrelated process. A pid the scan never saw has nothing to compare
+    // against and counts as unchanged.
+    pub fn check_pid(&self, pid: u32) -> PidCheck {
+        let mut fresh = System::new();
+        fresh.refresh_processes(ProcessesToUpdate::Some(&[Pid::from_u32(pid)]), true);
+        let Some(now) = fresh.process(Pid::from_u32(pid)) else {
+            return PidCheck::Gone;
+        };
+        match self.scanned.get(&pid) {
+            Some((name, start_time)) if now.name() != name || now.start_time() != *start_time => {
+                PidCheck::Reused(format!(
+                    "now: {}, started {} ago",
+                    process_name(now.name()),
+                    human_readable_date(now.run_time())
+                ))
+            }
+            _ => PidCheck::Unchanged,
+        }
+    }
+
+    // With `dry_run` the outcome only says what would have been sent.
+    pub fn kill_process_by_pid(
+        &self,
+        pid: u32,
+        process: &Process,
+        signal: Signal,
+        dry_run: bool,
+    ) -> KillOutcome {
+        let mut outcome = KillOutcome {
+            pid,
+            name: process_name(process.name()),
+            signal,
+            status: KillStatus::Failed,
+            note: None,
+            relaunch: None,
+        };
+        if process.status() == ProcessStatus::Zombie {
+            outcome.note = Some(zombie_note(process));
+        }
+        if dry_run {
+            outcome.status = KillStatus::DryRun;
+            return outcome;
+        }
+
+        match self.check_pid(pid) {
+            PidCheck::Unchanged => {}
+            PidCheck::Gone => {
+                outcome.status = KillStatus::AlreadyGone;
+                return outcome;
+            }
+            PidCheck::Reused(now) => {
+                outcome.status = KillStatus::Reused;
+                outcome.note = Some(now);
+                return outcome;
+            }
+        }
+
+        // the command line goes away with the process
+        let relaunch = LaunchSpec::capture(process);
+        let sent = match process.kill_with(signal) {
+            Some(sent) => sent,
+            // Windows can only terminate, which is close enough for the
+            // signals that ask a process to exit but not for the others
+            None if matches!(signal, Signal::Term | Signal::Interrupt | Signal::Kill) => {
+                outcome.note = Some(format!(
+                    "{} is not supported on this platform, terminated pid {} instead",
+                    signal, pid
+                ));
+                process.kill()
+            }
+            None => {
+                outcome.note = Some(format!("{} is not supported on this platform", signal));
+                false
+            }
+        };
+        outcome.status = if !sent {
+            KillStatus::Failed
+        } else if !matches!(signal, Signal::Term | Signal::Interrupt | Signal::Kill) {
+            KillStatus::Sent
+        } else if process.status() == ProcessStatus::Zombie {
+            // `wait_for_exit` counts zombies as gone, but this one already was
+            KillStatus::StillRunning
+        } else if wait_for_exit(pid, KILL_CHECK_PERIOD) {
+            KillStatus::Terminated
+        } else {
+            KillStatus::StillRunning
+        };
+        if outcome.status == KillStatus::Terminated {
+            outcome.relaunch = Some(relaunch);
+        }
+        self.log_signal(process, signal, outcome.status.label());
+        outcome
+    }
+
+    // Appends to the history log unless --no-log or `history = false` turned
+    // it off. Expects `load_launch_details` to have run.
+    pub fn log_signal(&self, process: &Process, signal: Signal, outcome: &str) {
+        if !self.log_kills {
+            return;
+        }
+        let ports = self.ports_of(&HashSet::from([process.pid().as_u32()]));
+        history::record(&history::Entry::new(process, ports, signal, outcome));
+    }
+
+    // The list scan skips command lines and working directories, the history
+    // log and the relaunch line after a kill want them.
+    pub fn load_launch_details(&mut self) {
+        self.system_info.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            false,
+            ProcessRefreshKind::nothing()
+                .with_cmd(UpdateKind::OnlyIfNotSet)
+                .with_cwd(UpdateKind::OnlyIfNotSet)
+                .with_exe(UpdateKind::OnlyIfNotSet),
+        );
+    }
+
+    // The process behind `pid` and the ones that launched it, nearest first.
+    pub fn ancestry(&self, pid: u32) -> Vec<&Process> {
+        lineage(self.system_info.processes(), Pid::from_u32(pid))
+    }
+
+    // sysinfo only records parent links, so invert them once per walk.
+    pub fn children_by_parent(&self) -> HashMap<Pid, Vec<Pid>> {
+        let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+        for (pid, process) in self.system_info.processes() {
+            if let Some(parent) = process.parent() {
+                children.entry(parent).or_default().push(*pid);
+            }
+        }
+        for kids in children.values_mut() {
+            kids.sort();
+        }
+
+        children
+    }
+
+    // Returns `root` followed by all of its descendants in breadth-first order,
+    // so iterating in reverse visits children before their parents. The
+    // `visited` set guards against cycles in the parent links, which can show up
+    // when PIDs are reused while we walk the table.
+    pub fn process_tree(&self, root: u32) -> Vec<u32> {
+        let children = self.children_by_parent();
+
+        let mut tree = vec![];
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::from([Pid::from_u32(root)]);
+        while let Some(pid) = queue.pop_front() {
+            if !visited.insert(pid) {
+                continue;
+            }
+            tree.push(pid.as_u32());
+            if let Some(kids) = children.get(&pid) {
+                queue.extend(kids);
+            }
+        }
+
+        tree
+    }
+
+    // Protected descendants are skipped unless `allow_protected`, whether the
+    // root itself may go is up to the caller.
+    pub fn kill_process_tree(
+        &self,
+        pid: u32,
+        signal: Signal,
+        dry_run: bool,
+        allow_protected: bool,
+    ) -> Vec<KillOutcome> {
+        let mut outcomes = vec![];
+        for member in self.process_tree(pid).into_iter().rev() {
+            if member != pid
+                && let Some(mut refused) = self.refuse_protected(member, signal, allow_protected)
+            {
+                refused.note = Some(format!("in the tree of pid {}", pid));
+                outcomes.push(refused);
+                continue;
+            }
+            // children may exit on their own once their parent or siblings die
+            let mut outcome = match self.system_info.process(Pid::from_u32(member)) {
+                Some(process) => self.kill_process_by_pid(member, process, signal, dry_run),
+                None => KillOutcome::gone(member, signal),
+            };
+            // the root starts its children again
+            if member != pid {
+                outcome.relaunch = None;
+            }
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    // The pids a kill is aimed at, sorted. A process holding several of the
+    // ports is only listed once. With `older_than`, processes younger than
+    // that are left out, as are ones that exited since the scan.
+    pub fn target_pids(&self, request: &KillRequest) -> Vec<u32> {
+        let mut pids: Vec<u32> = if !request.ports.is_empty() {
+            request
+                .ports
+                .iter()
+                .flat_map(|port| self.pids_on_port(*port))
+                .collect()
+        } else if let Some(pid) = request.pid {
+            vec![pid]
+        } else if let Some(name) = &request.name {
+            self.pids_named(name)
+        } else {
+            vec![]
+        };
+        if let Some(min_age) = request.older_than {
+            pids.retain(|pid| {
+                self.system_info
+                    .process(Pid::from_u32(*pid))
+                    .is_some_and(|process| process.run_time() >= min_age)
+            });
+        }
+        pids.sort();
+        pids.dedup();
+        pids
+    }
+
+    // Signals every target of `request`, relaunching them for --restart is up
+    // to the caller.
+    pub fn kill_target(&self, request: &KillRequest) -> Vec<KillOutcome> {
+        let signal = request.signal;
+        let mut outcomes = vec![];
+        for pid in self.target_pids(request) {
+            if let Some(refused) = self.refuse_protected(pid, signal, request.allow_protected) {
+                outcomes.push(refused);
+                continue;
+            }
+
+            if request.tree {
+                outcomes.extend(self.kill_process_tree(
+                    pid,
+                    signal,
+                    request.dry_run,
+                    request.allow_protected,
+                ));
+                continue;
+            }
+
+            // processes often exit between the scan and the kill, that's no
+            // reason to spare the rest
+            let outcome = match self.system_info.process(Pid::from_u32(pid)) {
+                Some(process) => self.kill_process_by_pid(pid, process, signal, request.dry_run),
+                None => KillOutcome::gone(pid, signal),
+            };
+            outcomes.push(outcome);
+        }
+        outcomes
+    }
+
+    // The outcome for a protected target, unless `allow` overrides it.
+    pub fn refuse_protected(&self, pid: u32, signal: Signal, allow: bool) -> Option<KillOutcome> {
+        if allow {
+            return None;
+        }
+        let process = self.system_info.process(Pid::from_u32(pid))?;
+        let name = process_name(process.name());
+        if !self.is_protected(pid, &name) {
+            return None;
+        }
+        Some(KillOutcome {
+            pid,
+            name,
+            signal,
+            status: KillStatus::Protected,
+            note: None,
+            relaunch: None,
+        })
+    }
+
+    // Socket-holding processes whose name matches, sorted by pid.
+    pub fn pids_named(&self, name: &Regex) -> Vec<u32> {
+        let mut pids: Vec<u32> = self
+            .by_process
+            .iter()
+            .filter(|(_, indexes)| {
+                self.entry(indexes[0])
+                    .is_some_and(|info| name.is_match(&info.process_name))
+            })
+            .map(|(pid, _)| *pid)
+            .collect();
+        pids.sort();
+        pids
+    }
+
+    // The ports `pids` held at the last scan, sorted.
+    pub fn ports_of(&self, pids: &HashSet<u32>) -> Vec<u16> {
+        let mut ports: Vec<u16> = pids
+            .iter()
+            .flat_map(|pid| self.ports_for_pid(*pid))
+            .map(|info| info.port_number)
+            .collect();
+        ports.sort();
+        ports.dedup();
+        ports
+    }
+
+    // Who holds `port` right now, or None if the sockets can't be read. Only
+    // the processes found on the port are refreshed, so this is cheap enough
+    // to poll, unlike a full `refresh`.
+    pub fn current_holders(&mut self, port: u16) -> Option<Vec<(u32, String)>> {
+        let sockets = self
+            .source
+            .sockets(self.address_families, self.protocols)
+            .ok()?;
+
+        let mut pids: Vec<u32> = sockets
+            .iter()
+            .filter(|socket| socket.local_port == port)
+            .flat_map(|socket| socket.pids.iter().copied())
+            .collect();
+        pids.sort();
+        pids.dedup();
+        let sysinfo_pids: Vec<Pid> = pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+        self.system_info.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&sysinfo_pids),
+            true,
+            ProcessRefreshKind::nothing(),
+        );
+
+        Some(
+            pids.into_iter()
+                .filter_map(|pid| {
+                    let process = self.system_info.process(Pid::from_u32(pid))?;
+                    (process.status() != ProcessStatus::Zombie)
+                        .then(|| (pid, process_name(process.name())))
+                })
+                .collect(),
+        )
+    }
+}
+
+impl Default for Manager {
+    fn default() -> Manager {
+        Manager::new()
+    }
+}
+
+// Only what the list needs: names, parents, status and start times. Command
+// lines and resource usage are loaded per process by `refresh_process_details`
+// when something actually shows them.
+fn list_refresh_kind() -> ProcessRefreshKind {
+    ProcessRefreshKind::nothing()
+        .without_tasks()
+        .with_user(UpdateKind::OnlyIfNotSet)
+}
+
+// What a `Manager::rescan` found changed, matched by `PortInfo::key`.
+#[derive(Debug, Clone, Default)]
+pub struct RescanDiff {
+    pub added: Vec<PortInfo>,
+    pub removed: Vec<PortInfo>,
+}
+
+impl RescanDiff {
+    fn between(before: &[PortInfo], after: &[PortInfo]) -> RescanDiff {
+        let old: HashSet<_> = before.iter().map(PortInfo::key).collect();
+        let new: HashSet<_> = after.iter().map(PortInfo::key).collect();
+        RescanDiff {
+            added: after
+                .iter()
+                .filter(|info| !old.contains(&info.key()))
+                .cloned()
+                .collect(),
+            removed: before
+                .iter()
+                .filter(|info| !new.contains(&info.key()))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+impl fmt::Display for RescanDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} new, {} gone", self.added.len(), self.removed.len())
+    }
+}
+
+// Kernel threads and processes caught mid-exec can report an empty name, which
+// would otherwise render as a blank column.
+pub fn process_name(name: &OsStr) -> String {
+    if name.is_empty() {
+        String::from("(unknown)")
+    } else {
+        name.to_string_lossy().into_owned()
+    }
+}
+
+// Local times carry their offset, e.g. "2024-05-12 14:03:11 +02:00", so they
+// can't be mistaken for UTC. Some platforms and containers report 0 or garbage
+// for the start time, nothing real started in 1970.
+pub fn format_start_time<Tz: TimeZone>(secs: u64, tz: &Tz, utc: bool) -> String
+where
+    Tz::Offset: fmt::Display,
+{
+    if secs == 0 {
+        return String::from("unavailable");
+    }
+    match i64::try_from(secs).map(|secs| Utc.timestamp_opt(secs, 0)) {
+        Ok(LocalResult::Single(start_time)) if utc => {
+            start_time.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+        }
+        Ok(LocalResult::Single(start_time)) => start_time
+            .with_timezone(tz)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string(),
+        _ => String::from("unavailable"),
+    }
+}
+
+pub fn human_readable_date(secs: u64) -> String {
+    let days = secs / 86400;
+    let hours = (secs % 86400) / 3600;
+    let minutes = (secs % 3600) / 60;
+    let seconds = secs % 60;
+
+    match (days, hours, minutes, seconds) {
+        (0, 0, 0, s) => format!("{s}s"),
+        (0, 0, m, s) => format!("{m}m {s}s"),
+        (0, h, m, s) => format!("{h}h {m}m {s}s"),
+        (d, h, m, s) => format!("{d}d {h}h {m}m {s}s"),
+    }
+}
+
+pub fn human_readable_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    // compared as printed, or 1048575 B would show as "1024.0 KiB"
+    while (value * 10.0).round() >= 10240.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+// Keeps the first `limit` rows, returning "(showing 20 of 142)" if any were cut.
+pub fn truncate_rows<T>(rows: &mut Vec<T>, limit: Option<NonZeroUsize>) -> Option<String> {
+    let total = rows.len();
+    let limit = limit?.get();
+    if total <= limit {
+        return None;
+    }
+    rows.truncate(limit);
+    Some(format!("(showing {} of {})", limit, total))
+}
+
+pub fn sorted_port_infos(port_infos: &[PortInfo], sort: Option<SortKey>) -> Vec<PortInfo> {
+    sorted_indices(port_infos, sort)
+        .into_iter()
+        .map(|i| port_infos[i].clone())
+        .collect()
+}
+
+// The order `sort` puts `port_infos` in, as indices into it.
+pub fn sorted_indices(port_infos: &[PortInfo], sort: Option<SortKey>) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..port_infos.len()).collect();
+    match sort {
+        Some(SortKey::Port) => order.sort_by_key(|i| port_infos[*i].port_number),
+        Some(SortKey::Pid) => order.sort_by_key(|i| port_infos[*i].pid),
+        Some(SortKey::Name) => order.sort_by(|a, b| {
+            port_infos[*a]
+                .process_name
+                .cmp(&port_infos[*b].process_name)
+        }),
+        Some(SortKey::Protocol) => order.sort_by_key(|i| port_infos[*i].protocol as u8),
+        Some(SortKey::Age) => order.sort_by_key(|i| Reverse(port_infos[*i].run_time)),
+        None => {}
+    }
+    order
+}
+
+fn serialize_display<T: fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    // Sockets are faked, the processes behind them are not, so the tests
+    // hand them to pids that exist: the test itself and init.
+    const INIT: u32 = 1;
+
+    fn own() -> u32 {
+        process::id()
+    }
+
+    fn listener(addr: IpAddr, port: u16, pids: &[u32]) -> SocketRecord {
+        SocketRecord {
+            local_addr: addr,
+            local_port: port,
+            remote: None,
+            protocol: ProtocolInfo::TCP,
+            state: SocketState::Listen,
+            pids: pids.to_vec(),
+            inode: None,
+        }
+    }
+
+    fn loopback(port: u16, pids: &[u32]) -> SocketRecord {
+        listener(IpAddr::V4(Ipv4Addr::LOCALHOST), port, pids)
+    }
+
+    fn collect(sockets: Vec<SocketRecord>) -> Manager {
+        Manager::collect(sockets, CollectOptions::default()).unwrap()
+    }
+
+    // Every entry sits under its own port and pid exactly once, and nothing
+    // else does.
+    fn assert_indexes_consistent(manager: &Manager) {
+        let mut by_port: Vec<usize> = vec![];
+        for (port, indexes) in &manager.by_port {
+            for index in indexes {
+                assert_eq!(manager.port_infos[*index].port_number, *port);
+            }
+            by_port.extend(indexes);
+        }
+        let mut by_process: Vec<usize> = vec![];
+        for (pid, indexes) in &manager.by_process {
+            for index in indexes {
+                assert_eq!(manager.port_infos[*index].pid, *pid);
+            }
+            by_process.extend(indexes);
+        }
+        let all: Vec<usize> = (0..manager.port_infos.len()).collect();
+        by_port.sort();
+        by_process.sort();
+        assert_eq!(by_port, all);
+        assert_eq!(by_process, all);
+    }
+
+    #[test]
+    fn shared_socket_lists_every_holder() {
+        let manager = collect(vec![loopback(8080, &[own(), INIT])]);
+
+        assert_eq!(manager.port_infos.len(), 2);
+        let mut expected = vec![INIT, own()];
+        expected.sort();
+        assert_eq!(manager.pids_on_port(8080), expected);
+        for info in &manager.port_infos {
+            assert_eq!(info.socket_pids, vec![own(), INIT]);
+        }
+        // forked workers sharing one socket don't fight over it
+        assert!(manager.port_conflicts().is_empty());
+        assert_indexes_consistent(&manager);
+    }
+
+    #[test]
+    fn separate_listeners_on_one_port_conflict() {
+        let manager = collect(vec![loopback(8080, &[own()]), loopback(8080, &[INIT])]);
+
+        let conflicts = manager.port_conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0, 8080);
+        assert_eq!(conflicts[0].1, ProtocolInfo::TCP);
+    }
+
+    #[test]
+    fn sockets_without_pids_are_counted_not_listed() {
+        let mut time_wait = loopback(9000, &[]);
+        time_wait.state = SocketState::TimeWait;
+        let manager = collect(vec![loopback(8080, &[]), time_wait]);
+
+        assert!(manager.port_infos.is_empty());
+        assert!(manager.pids_on_port(8080).is_empty());
+        // TIME_WAIT belongs to nobody, only the listener needs sudo to see
+        assert_eq!(
+            manager.privilege_hint().as_deref(),
+            Some("1 socket could not be attributed to a process; try running with sudo")
+        );
+        assert_indexes_consistent(&manager);
+    }
+
+    #[test]
+    fn exited_holders_are_left_out() {
+        // far past any pid_max
+        let manager = collect(vec![loopback(8080, &[999_999_999, own()])]);
+
+        assert_eq!(manager.pids_on_port(8080), vec![own()]);
+        assert_indexes_consistent(&manager);
+    }
+
+    #[test]
+    fn dual_stack_listener_is_one_process() {
+        let sockets = vec![
+            listener(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 8080, &[own()]),
+            listener(IpAddr::V6(Ipv6Addr::UNSPECIFIED), 8080, &[own()]),
+        ];
+        let manager = collect(sockets.clone());
+
+        assert_eq!(manager.port_infos.len(), 2);
+        assert_eq!(manager.pids_on_port(8080), vec![own()]);
+        assert_eq!(manager.ports_for_pid(own()).len(), 2);
+        assert!(manager.port_conflicts().is_empty());
+        assert_indexes_consistent(&manager);
+
+        let options = CollectOptions {
+            address_families: AddressFamilyFlags::IPV4,
+            ..CollectOptions::default()
+        };
+        let manager = Manager::collect(sockets, options).unwrap();
+        assert_eq!(manager.port_infos.len(), 1);
+        assert!(manager.port_infos[0].local_addr.is_ipv4());
+    }
+
+    #[test]
+    fn indexes_survive_filtering_and_refresh() {
+        let mut udp = loopback(5353, &[own()]);
+        udp.protocol = ProtocolInfo::UDP;
+        udp.state = SocketState::Stateless;
+        let sockets = vec![
+            loopback(8080, &[own()]),
+            udp,
+            loopback(8081, &[INIT]),
+            loopback(8080, &[INIT]),
+            loopback(9000, &[]),
+        ];
+        let mut manager = collect(sockets);
+        assert_eq!(manager.port_infos.len(), 4);
+        assert_indexes_consistent(&manager);
+
+        let filters = Filters {
+            ports: vec![8080, 5353],
+            ..Filters::default()
+        };
+        manager.refresh(&filters).unwrap();
+        assert_eq!(manager.port_infos.len(), 3);
+        assert!(manager.find_by_port(8081).is_empty());
+        assert_eq!(manager.ports_for_pid(INIT).len(), 1);
+        assert_indexes_consistent(&manager);
+
+        // a rescan keeps the filters of the last refresh
+        assert!(manager.rescan().unwrap().is_empty());
+        assert_eq!(manager.port_infos.len(), 3);
+        assert_indexes_consistent(&manager);
+    }
+
+    #[test]
+    fn find_by_port_returns_entries_in_scan_order() {
+        let mut udp = loopback(8080, &[INIT]);
+        udp.protocol = ProtocolInfo::UDP;
+        udp.state = SocketState::Stateless;
+        let manager = collect(vec![
+            loopback(8080, &[own()]),
+            loopback(9000, &[own()]),
+            udp,
+        ]);
+
+        let found: Vec<(u32, ProtocolInfo)> = manager
+            .find_by_port(8080)
+            .iter()
+            .map(|info| (info.pid, info.protocol))
+            .collect();
+        assert_eq!(
+            found,
+            vec![(own(), ProtocolInfo::TCP), (INIT, ProtocolInfo::UDP)]
+        );
+        assert!(manager.find_by_port(8081).is_empty());
+        // references into the scan, not copies
+        for info in manager.find_by_port(9000) {
+            assert!(
+                manager
+                    .port_infos
+                    .iter()
+                    .any(|entry| std::ptr::eq(entry, info))
+            );
+        }
+    }
+
+    #[test]
+    fn lookups_agree_with_the_scan() {
+        let sockets = vec![
+            loopback(8080, &[own()]),
+            loopback(8443, &[own()]),
+            loopback(8080, &[INIT]),
+        ];
+        let manager = collect(sockets);
+
+        let mut expected = vec![INIT, own()];
+        expected.sort();
+        assert_eq!(manager.pids_on_port(8080), expected);
+        assert_eq!(manager.pids_on_port(8443), vec![own()]);
+        assert!(manager.pids_on_port(9000).is_empty());
+
+        let mut ports: Vec<u16> = manager
+            .ports_for_pid(own())
+            .iter()
+            .map(|info| info.port_number)
+            .collect();
+        ports.sort();
+        assert_eq!(ports, vec![8080, 8443]);
+        assert!(manager.ports_for_pid(999_999_999).is_empty());
+
+        for (index, info) in manager.port_infos.iter().enumerate() {
+            assert_eq!(manager.entry(index), Some(info));
+        }
+        assert!(manager.entry(manager.port_infos.len()).is_none());
+    }
+
+    #[test]
+    fn entries_match_by_port_or_name() {
+        let manager = collect(vec![loopback(8080, &[own()]), loopback(8443, &[INIT])]);
+        let own_name = manager.ports_for_pid(own())[0].process_name.clone();
+
+        let on_port = manager.entries_matching(" 8443 ");
+        assert_eq!(on_port.len(), 1);
+        assert_eq!(on_port[0].pid, INIT);
+        // a port number never matches a name
+        assert!(manager.entries_matching("80").is_empty());
+        let named = manager.entries_matching(&own_name.to_uppercase());
+        assert!(named.iter().any(|info| info.pid == own()));
+        assert!(named.iter().all(|info| info.process_name == own_name));
+        assert_eq!(manager.entries_matching("").len(), 2);
+    }
+
+    #[test]
+    fn self_exclude_hides_plug() {
+        let sockets = vec![
+            loopback(8080, &[own()]),
+            loopback(8443, &[own(), INIT]),
+            loopback(9000, &[INIT]),
+        ];
+        let options = CollectOptions {
+            filters: Filters {
+                self_exclude: true,
+                ..Filters::default()
+            },
+            ..CollectOptions::default()
+        };
+        let manager = Manager::collect(sockets.clone(), options).unwrap();
+
+        assert!(manager.port_infos.iter().all(|info| info.pid != own()));
+        assert!(manager.ports_for_pid(own()).is_empty());
+        assert!(manager.pids_on_port(8080).is_empty());
+        assert_eq!(manager.pids_on_port(8443), vec![INIT]);
+        assert_eq!(manager.pids_on_port(9000), vec![INIT]);
+        assert_indexes_consistent(&manager);
+
+        // off by default
+        assert_eq!(collect(sockets).pids_on_port(8080), vec![own()]);
+    }
+
+    #[test]
+    fn protocol_parses_in_either_case() {
+        for text in ["tcp", "TCP", "Tcp"] {
+            assert_eq!(text.parse(), Ok(ProtocolInfo::TCP));
+        }
+        for text in ["udp", "UDP", "uDp"] {
+            assert_eq!(text.parse(), Ok(ProtocolInfo::UDP));
+        }
+        assert_eq!(
+            "sctp".parse::<ProtocolInfo>(),
+            Err(String::from(
+                "unknown protocol \"sctp\", expected tcp or udp"
+            ))
+        );
+        assert!("".parse::<ProtocolInfo>().is_err());
+    }
+
+    #[test]
+    fn protocol_round_trips() {
+        for (protocol, text) in [(ProtocolInfo::TCP, "tcp"), (ProtocolInfo::UDP, "udp")] {
+            assert_eq!(protocol.to_string(), text);
+            assert_eq!(protocol.to_string().parse(), Ok(protocol));
+
+            let json = serde_json::to_string(&protocol).unwrap();
+            assert_eq!(json, format!("\"{}\"", text));
+            assert_eq!(
+                serde_json::from_str::<ProtocolInfo>(&json).unwrap(),
+                protocol
+            );
+        }
+        // upper case is read back too
+        assert_eq!(
+            serde_json::from_str::<ProtocolInfo>("\"UDP\"").unwrap(),
+            ProtocolInfo::UDP
+        );
+        let err = serde_json::from_str::<ProtocolInfo>("\"icmp\"").unwrap_err();
+        assert!(err.to_string().contains("expected tcp or udp"));
+    }
+
+    fn outcome(pid: u32, status: KillStatus) -> KillOutcome {
+        KillOutcome {
+            status,
+            ..KillOutcome::gone(pid, Signal::Kill)
+        }
+    }
+
+    #[test]
+    fn kill_summary_counts_each_status() {
+        let outcomes = vec![
+            outcome(10, KillStatus::Terminated),
+            outcome(11, KillStatus::Terminated),
+            outcome(12, KillStatus::AlreadyGone),
+        ];
+        assert_eq!(
+            kill_summary(&outcomes),
+            "2 killed, 1 already gone, 0 failed"
+        );
+
+        let outcomes = vec![
+            outcome(10, KillStatus::Failed),
+            outcome(11, KillStatus::Sent),
+            outcome(12, KillStatus::StillRunning),
+            outcome(13, KillStatus::Reused),
+            outcome(14, KillStatus::Protected),
+        ];
+        assert_eq!(
+            kill_summary(&outcomes),
+            "0 killed, 0 already gone, 1 failed, 1 signalled, 1 still running, \
+             1 pids reused, 1 protected skipped"
+        );
+    }
+
+    #[test]
+    fn failures_outrank_survivors_in_the_exit_code() {
+        assert_eq!(kill_exit_code(&[]), 0);
+        let clean = [
+            outcome(10, KillStatus::Terminated),
+            outcome(11, KillStatus::AlreadyGone),
+            outcome(12, KillStatus::Protected),
+        ];
+        assert_eq!(kill_exit_code(&clean), 0);
+        let lingering = [
+            outcome(10, KillStatus::Terminated),
+            outcome(11, KillStatus::StillRunning),
+        ];
+        assert_eq!(kill_exit_code(&lingering), EXIT_STILL_RUNNING);
+        for failure in [KillStatus::Failed, KillStatus::Reused] {
+            let outcomes = [outcome(10, KillStatus::StillRunning), outcome(11, failure)];
+            assert_eq!(kill_exit_code(&outcomes), EXIT_KILL_FAILED);
+        }
+    }
+
+    #[test]
+    fn truncate_rows_reports_the_cut() {
+        let mut rows: Vec<u32> = (0..5).collect();
+        assert_eq!(truncate_rows(&mut rows, None), None);
+        assert_eq!(truncate_rows(&mut rows, NonZeroUsize::new(5)), None);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(
+            truncate_rows(&mut rows, NonZeroUsize::new(2)).as_deref(),
+            Some("(showing 2 of 5)")
+        );
+        assert_eq!(rows, vec![0, 1]);
+    }
+
+    #[test]
+    fn sorted_indices_follow_the_key() {
+        let manager = collect(vec![
+            loopback(9000, &[own()]),
+            loopback(8080, &[INIT]),
+            loopback(8443, &[own()]),
+        ]);
+        let ports = |sort| -> Vec<u16> {
+            sorted_indices(&manager.port_infos, sort)
+                .into_iter()
+                .map(|i| manager.port_infos[i].port_number)
+                .collect()
+        };
+
+        assert_eq!(ports(None), vec![9000, 8080, 8443]);
+        assert_eq!(ports(Some(SortKey::Port)), vec![8080, 8443, 9000]);
+        // stable, so sockets of one process keep their scan order
+        let mut by_pid = [(INIT, 8080), (own(), 9000), (own(), 8443)];
+        by_pid.sort_by_key(|(pid, _)| *pid);
+        let by_pid: Vec<u16> = by_pid.iter().map(|(_, port)| *port).collect();
+        assert_eq!(ports(Some(SortKey::Pid)), by_pid);
+        // init has been up longest
+        assert_eq!(ports(Some(SortKey::Age))[0], 8080);
+
+        let sorted = sorted_port_infos(&manager.port_infos, Some(SortKey::Port));
+        let sorted: Vec<u16> = sorted.iter().map(|info| info.port_number).collect();
+        assert_eq!(sorted, vec![8080, 8443, 9000]);
+    }
+
+    #[test]
+    fn target_pids_follow_the_request() {
+        let manager = collect(vec![
+            loopback(8080, &[own()]),
+            loopback(8443, &[own(), INIT]),
+        ]);
+        let own_name = manager.ports_for_pid(own())[0].process_name.clone();
+        let mut both = vec![INIT, own()];
+        both.sort();
+
+        let by_ports = KillRequest {
+            ports: vec![8080, 8443, 9000],
+            ..KillRequest::default()
+        };
+        assert_eq!(manager.target_pids(&by_ports), both);
+        let by_pid = KillRequest {
+            pid: Some(INIT),
+            ..KillRequest::default()
+        };
+        assert_eq!(manager.target_pids(&by_pid), vec![INIT]);
+        let by_name = KillRequest {
+            name: Some(Regex::new(&regex::escape(&own_name)).unwrap()),
+            ..KillRequest::default()
+        };
+        assert_eq!(manager.target_pids(&by_name), vec![own()]);
+        assert!(manager.target_pids(&KillRequest::default()).is_empty());
+
+        // nothing here has been running for a century
+        let old = KillRequest {
+            older_than: Some(100 * 365 * 86400),
+            ..by_ports.clone()
+        };
+        assert!(manager.target_pids(&old).is_empty());
+        let started = KillRequest {
+            older_than: Some(0),
+            ..by_ports
+        };
+        assert_eq!(manager.target_pids(&started), both);
+    }
+
+    #[test]
+    fn ancestry_walks_up_to_init() {
+        let manager = collect(vec![]);
+
+        let chain = manager.ancestry(own());
+        assert_eq!(chain[0].pid().as_u32(), own());
+        for pair in chain.windows(2) {
+            assert_eq!(pair[0].parent(), Some(pair[1].pid()));
+        }
+        assert!(manager.ancestry(999_999_999).is_empty());
+        // plug's own lineage is protected
+        assert!(chain.iter().all(|process| {
+            manager.is_protected(process.pid().as_u32(), &process_name(process.name()))
+        }));
+    }
+
+    #[test]
+    fn empty_process_names_show_as_unknown() {
+        assert_eq!(process_name(OsStr::new("")), "(unknown)");
+        assert_eq!(process_name(OsStr::new("nginx")), "nginx");
+        // only an empty name is replaced, not a blank one
+        assert_eq!(process_name(OsStr::new(" ")), " ");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_process_names_are_kept() {
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(process_name(OsStr::from_bytes(b"srv\xff")), "srv\u{fffd}");
+    }
+
+    #[test]
+    fn start_times_carry_their_offset() {
+        // 2024-05-12 14:03:11 UTC
+        let secs = 1_715_522_591;
+        let east = FixedOffset::east_opt(2 * 3600).unwrap();
+        let west = FixedOffset::west_opt(5 * 3600 + 30 * 60).unwrap();
+
+        assert_eq!(
+            format_start_time(secs, &east, false),
+            "2024-05-12 16:03:11 +02:00"
+        );
+        assert_eq!(
+            format_start_time(secs, &west, false),
+            "2024-05-12 08:33:11 -05:30"
+        );
+        // --utc ignores the local zone
+        assert_eq!(
+            format_start_time(secs, &east, true),
+            "2024-05-12 14:03:11 UTC"
+        );
+        assert_eq!(format_start_time(0, &east, false), "unavailable");
+        assert_eq!(format_start_time(u64::MAX, &east, true), "unavailable");
+    }
+
+    #[test]
+    fn bytes_pick_the_unit_after_rounding() {
+        assert_eq!(human_readable_bytes(0), "0 B");
+        assert_eq!(human_readable_bytes(1023), "1023 B");
+        assert_eq!(human_readable_bytes(1024), "1.0 KiB");
+        assert_eq!(human_readable_bytes(1536), "1.5 KiB");
+        assert_eq!(human_readable_bytes(1_048_524), "1023.9 KiB");
+        assert_eq!(human_readable_bytes(1_048_575), "1.0 MiB");
+        assert_eq!(human_readable_bytes(1 << 20), "1.0 MiB");
+        assert_eq!(human_readable_bytes((1 << 30) - 1), "1.0 GiB");
+        assert_eq!(human_readable_bytes(1_443_862_528), "1.3 GiB");
+        assert_eq!(human_readable_bytes(5 << 30), "5.0 GiB");
+        // TiB is the largest unit
+        assert_eq!(human_readable_bytes(1 << 50), "1024.0 TiB");
+    }
+
+    // The menu lists a process, which exits before the pick is acted on.
+    #[cfg(unix)]
+    #[test]
+    fn process_exiting_after_the_scan_is_not_signalled() {
+        let mut child = process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let manager = collect(vec![loopback(8080, &[pid])]);
+        assert_eq!(manager.pids_on_port(8080), vec![pid]);
+        assert_eq!(manager.check_pid(pid), PidCheck::Unchanged);
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+
+        assert_eq!(manager.check_pid(pid), PidCheck::Gone);
+        let listed = manager.system_info.process(Pid::from_u32(pid)).unwrap();
+        let outcome = manager.kill_process_by_pid(pid, listed, Signal::Term, false);
+        assert_eq!(outcome.status, KillStatus::AlreadyGone);
+        let outcomes = manager.kill_process_tree(pid, Signal::Term, false, false);
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, KillStatus::AlreadyGone);
+    }
+
+    // One holder of the port exits between the scan and the kill, the other
+    // still has to be killed.
+    #[cfg(unix)]
+    #[test]
+    fn kill_carries_on_past_an_exited_holder() {
+        let mut exited = process::Command::new("sleep").arg("30").spawn().unwrap();
+        let mut running = process::Command::new("sleep").arg("30").spawn().unwrap();
+        let manager = collect(vec![loopback(8080, &[exited.id(), running.id()])]);
+        exited.kill().unwrap();
+        exited.wait().unwrap();
+
+        let request = KillRequest {
+            ports: vec![8080],
+            signal: Signal::Term,
+            ..KillRequest::default()
+        };
+        let outcomes = manager.kill_target(&request);
+        running.wait().unwrap();
+
+        let status = |pid: u32| {
+            outcomes
+                .iter()
+                .find(|outcome| outcome.pid == pid)
+                .map(|outcome| outcome.status)
+        };
+        assert_eq!(outcomes.len(), 2);
+        assert_eq!(status(exited.id()), Some(KillStatus::AlreadyGone));
+        assert_eq!(status(running.id()), Some(KillStatus::Terminated));
+        assert_eq!(
+            kill_summary(&outcomes),
+            "1 killed, 1 already gone, 0 failed"
+        );
+        assert_eq!(kill_exit_code(&outcomes), 0);
+        assert_eq!(killed_pids(&outcomes), HashSet::from([running.id()]));
+    }
+
+    #[test]
+    fn pid_missing_from_the_scan_is_gone() {
+        let manager = collect(vec![]);
+
+        assert_eq!(manager.check_pid(999_999_999), PidCheck::Gone);
+        let outcomes = manager.kill_process_tree(999_999_999, Signal::Kill, false, false);
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].status, KillStatus::AlreadyGone);
+    }
+}
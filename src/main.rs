@@ -1,259 +1,1753 @@
-use chrono::{DateTime, Local, TimeZone, Utc};
-use core::fmt;
-use inquire::Select;
-use netstat2::{AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo};
+use actions::{Action, ActionResult, Context};
+use chrono::DateTime;
+use clap::{CommandFactory, Parser};
+use config::Config;
+use inquire::error::InquireResult;
+use inquire::validator::Validation;
+use inquire::{Confirm, CustomType, InquireError, Select, Text};
+use plug::cli::{Cli, Command, DetailsArgs, Filters, KillSignal, SnapshotAction, WaitArgs};
+use plug::details::ProcessDetails;
+use plug::environ;
+use plug::history;
+use plug::output::{self, OutputFormat};
+#[cfg(unix)]
+use plug::priority;
+use plug::relaunch::{LaunchSpec, wait_for_exit};
+use plug::snapshot;
+use plug::sockets::Netstat;
+use plug::{
+    CollectOptions, EXIT_STILL_RUNNING, KillOutcome, KillRequest, KillStatus, Manager, PidCheck,
+    PlugError, PortInfo, RescanDiff, human_readable_bytes, human_readable_date, kill_exit_code,
+    kill_summary, killed_pids, process_name, services, sorted_indices, sorted_port_infos,
+    truncate_rows,
+};
+#[cfg(target_os = "linux")]
+use plug::{container, fds};
 use std::collections::{HashMap, HashSet};
-use sysinfo::{Pid, Process, System};
+use std::env;
+use std::io::{self, IsTerminal};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use sysinfo::{Networks, Pid, Process, ProcessRefreshKind, ProcessesToUpdate, Signal, System};
 
-#[derive(Debug, Clone)]
-enum ProtocolInfo {
-    TCP,
-    UDP,
+mod actions;
+mod config;
+mod tui;
+
+// How many files or sockets "View Open Files" lists before summarizing the rest.
+const MAX_LISTED_FDS: usize = 20;
+
+// How many sockets "View Details" lists unless --all is given.
+const MAX_LISTED_SOCKETS: usize = 20;
+
+// How many ancestors "View Details" walks up from the selected process.
+const MAX_LISTED_ANCESTORS: usize = 3;
+
+// How many rows the interactive menu offers unless --limit says otherwise.
+// inquire renders every option up front, a busy server's 50k sockets would
+// stall the prompt for seconds.
+const MAX_MENU_ROWS: NonZeroUsize = NonZeroUsize::new(1000).unwrap();
+
+// Kills by name warn when they match more processes than this.
+const MAX_NAME_MATCHES: usize = 10;
+
+// How long a restarted process gets to exit after each signal.
+const RESTART_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// Exit code when the scan finds nothing, so scripts can tell an empty result
+// apart from success.
+const EXIT_NO_MATCHES: i32 = 1;
+
+// Exit code when `plug wait` gives up, the same one coreutils' timeout uses.
+const EXIT_TIMEOUT: i32 = 124;
+
+// Exit code after Ctrl-C at a prompt, 128 + SIGINT like a shell reports it.
+const EXIT_INTERRUPTED: i32 = 130;
+
+// Exit codes when plug itself fails: --output-file can't be written, the
+// socket table can't be read, or there's no terminal to ask questions on.
+const EXIT_WRITE_FAILED: i32 = 2;
+const EXIT_SCAN_FAILED: i32 = 2;
+const EXIT_PROMPT_FAILED: i32 = 2;
+
+// Pause between rescans while waiting for a port to come free.
+const PORT_RECHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// Set once in `main` from `should_colorize`.
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+// --no-color and NO_COLOR (set to anything, see no-color.org) always win, then
+// the `color` setting from the config file, and otherwise only terminals get
+// escape codes.
+fn should_colorize(no_color: bool, config_color: Option<bool>) -> bool {
+    if no_color || env::var_os("NO_COLOR").is_some() {
+        return false;
+    }
+    config_color.unwrap_or_else(|| io::stdout().is_terminal())
+}
+
+fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+fn red(text: &str) -> String {
+    if color_enabled() {
+        format!("\x1b[31m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+// A name can match far more than intended and an age spares some holders, so
+// kills by name or --older-than always list their targets and ask, unless
+// --yes was given.
+fn confirm_targets(manager: &Manager, request: &KillRequest, yes: bool) -> bool {
+    let pattern = request.name.as_ref();
+    let pids = manager.target_pids(request);
+    if pids.is_empty() {
+        match (request.older_than, pattern) {
+            (Some(min_age), _) => println!(
+                "nothing matching has been running longer than {}",
+                human_readable_date(min_age)
+            ),
+            (None, Some(pattern)) => {
+                println!("no socket-holding process matches {:?}", pattern.as_str())
+            }
+            (None, None) => {}
+        }
+        return false;
+    }
+
+    println!("Matching processes:");
+    for pid in &pids {
+        match manager.system_info.process(Pid::from_u32(*pid)) {
+            Some(process) => println!(
+                "  {} (pid {}) up {}",
+                process_name(process.name()),
+                pid,
+                human_readable_date(process.run_time())
+            ),
+            None => println!("  (unknown) (pid {})", pid),
+        }
+    }
+    if pattern.is_some() && pids.len() > MAX_NAME_MATCHES {
+        println!(
+            "{}",
+            red(&format!(
+                "warning: the pattern matches {} processes, check it isn't broader than intended",
+                pids.len()
+            ))
+        );
+    }
+    if yes {
+        return true;
+    }
+
+    let question = format!("Kill these {} processes?", pids.len());
+    matches!(
+        answered(Confirm::new(&question).with_default(false).prompt()),
+        Some(true)
+    )
+}
+
+// One line per port for `plug kill 3000 3001 8080`, e.g.
+// `3001   terminated node (4821)` or `8080   already free`.
+fn print_port_results(holders: &[(u16, Vec<u32>)], outcomes: &[KillOutcome]) {
+    println!("PORT   RESULT");
+    for (port, pids) in holders {
+        if pids.is_empty() {
+            println!("{:<5}  already free", port);
+            continue;
+        }
+        let results: Vec<String> = pids
+            .iter()
+            .map(
+                |pid| match outcomes.iter().find(|outcome| outcome.pid == *pid) {
+                    Some(outcome) => {
+                        format!("{} {} ({})", outcome.status.label(), outcome.name, pid)
+                    }
+                    None => format!("skipped ({})", pid),
+                },
+            )
+            .collect();
+        println!("{:<5}  {}", port, results.join(", "));
+    }
+}
+
+// The interactive follow-up to the relaunch lines `print_kill_outcomes` shows.
+fn offer_relaunch(outcomes: &[KillOutcome]) {
+    for outcome in outcomes {
+        let Some(Ok(spec)) = &outcome.relaunch else {
+            continue;
+        };
+        let question = format!("Relaunch {} now?", outcome.name);
+        if !matches!(
+            answered(Confirm::new(&question).with_default(false).prompt()),
+            Some(true)
+        ) {
+            continue;
+        }
+        match spec.spawn() {
+            Ok(new_pid) => println!("relaunched {} as pid {}", outcome.name, new_pid),
+            Err(err) => println!("failed to relaunch {}: {}", outcome.name, err),
+        }
+    }
 }
 
-enum Choices {
-    Kill,
-    ViewDetails,
+fn print_kill_outcomes(outcomes: &[KillOutcome]) {
+    for outcome in outcomes {
+        if let Some(note) = &outcome.note
+            && !matches!(outcome.status, KillStatus::Reused | KillStatus::Protected)
+        {
+            println!("{}", note);
+        }
+        println!("{}", outcome);
+        match &outcome.relaunch {
+            Some(Ok(spec)) => println!("  relaunch with: {}", spec.shell_line()),
+            Some(Err(reason)) => println!("  no relaunch command: {}", reason),
+            None => {}
+        }
+    }
+    if outcomes.len() > 1 {
+        println!("{}", kill_summary(outcomes));
+    }
 }
 
-impl fmt::Display for Choices {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Choices::Kill => write!(f, "Kill"),
-            Choices::ViewDetails => write!(f, "View Details"),
+// The socket half of "View Details", the process half is `ProcessDetails`.
+fn display_specs(picked: &PortInfo, specs: &ProcessDetails) {
+    println!("Port number: {}", picked.port_label());
+    println!("Port status: {}", picked.port_status);
+    println!("Local address: {}", picked.reachability());
+    print_process_details(specs);
+}
+
+fn print_process_details(details: &ProcessDetails) {
+    println!(
+        "Memory (resident): {}",
+        human_readable_bytes(details.memory_bytes)
+    );
+    println!(
+        "Memory (virtual): {}",
+        human_readable_bytes(details.virtual_memory_bytes)
+    );
+    // a large shared share is mostly mapped libraries, not the process's own weight
+    if let (Some(shared), Some(code), Some(data)) = (
+        details.shared_memory_bytes,
+        details.code_bytes,
+        details.data_bytes,
+    ) {
+        println!("Memory (shared): {}", human_readable_bytes(shared));
+        println!("Memory (code): {}", human_readable_bytes(code));
+        println!("Memory (data + stack): {}", human_readable_bytes(data));
+    }
+    // 100% means every core is busy, not just one
+    println!("CPU Usage: {:.1}%", details.cpu_percent);
+    #[cfg(unix)]
+    match details.nice {
+        Some(nice) => println!("Priority (nice): {}", nice),
+        None => println!("Priority (nice): (unavailable)"),
+    }
+    match details.threads {
+        Some(threads) => println!("Threads: {}", threads),
+        None => println!("Threads: n/a"),
+    }
+    println!("Process status: {}", details.status);
+    if let Some(note) = &details.zombie_note {
+        println!("{}", red(note));
+    }
+    match &details.disk {
+        Some(disk) => {
+            println!(
+                "Disk read: {} total, {} since last refresh",
+                human_readable_bytes(disk.read_bytes),
+                human_readable_bytes(disk.recent_read_bytes)
+            );
+            println!(
+                "Disk written: {} total, {} since last refresh",
+                human_readable_bytes(disk.written_bytes),
+                human_readable_bytes(disk.recent_written_bytes)
+            );
         }
+        None => {
+            println!("Disk read: unavailable (processes of other users need sudo)");
+            println!("Disk written: unavailable");
+        }
+    }
+    println!(
+        "Started: up {}, started {}",
+        human_readable_date(details.run_time_secs),
+        details.start_time_label
+    );
+    if details.stale {
+        println!(
+            "note: the run time and start time disagree by more than a minute, the data may be stale"
+        );
+    }
+    println!("Command: {:?}", details.cmd);
+    println!("Executable: {}", display_path(details.exe.as_deref()));
+    println!("Working dir: {}", display_path(details.cwd.as_deref()));
+}
+
+// "2024-05-12 14:32:07  Kill  node (pid 4821) on 3000  terminated  node server.js"
+fn print_history(entries: &[history::Entry]) {
+    for entry in entries {
+        let when = DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|_| entry.timestamp.clone());
+        let ports = if entry.ports.is_empty() {
+            String::new()
+        } else {
+            let ports: Vec<String> = entry.ports.iter().map(|port| port.to_string()).collect();
+            format!(" on {}", ports.join(", "))
+        };
+        let cmd = if entry.cmd.is_empty() {
+            String::from("(command unknown)")
+        } else {
+            entry.cmd.join(" ")
+        };
+        println!(
+            "{}  {}  {} (pid {}){}  {}  {}",
+            when, entry.signal, entry.name, entry.pid, ports, entry.outcome, cmd
+        );
     }
 }
 
-fn create_choices_vec() -> Vec<Choices> {
-    vec![Choices::Kill, Choices::ViewDetails]
+// "+ 8080/tcp python3 (pid 4321)" for new holders, "-" for ones that are gone.
+fn print_snapshot_diff(diff: &snapshot::Diff) {
+    if diff.added.is_empty() && diff.removed.is_empty() {
+        println!("nothing changed since the snapshot");
+        return;
+    }
+    let changes = diff
+        .added
+        .iter()
+        .map(|entry| ('+', entry))
+        .chain(diff.removed.iter().map(|entry| ('-', entry)));
+    for (sign, entry) in changes {
+        println!(
+            "{} {}/{} {} (pid {})",
+            sign, entry.port, entry.protocol, entry.name, entry.pid
+        );
+    }
 }
 
-#[derive(Debug)]
-struct PortInfo {
-    port_number: u16,
-    pid: u32,
-    process_name: String,
-    protocol: ProtocolInfo,
-    port_status: String,
+// The history log failing to write is only reported once per run.
+fn history_warning() -> Option<String> {
+    static REPORTED: AtomicBool = AtomicBool::new(false);
+    let err = history::write_error()?;
+    (!REPORTED.swap(true, Ordering::Relaxed)).then(|| format!("warning: {}", err))
 }
 
-impl fmt::Display for PortInfo {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}:{} -- {} Status: {} -- Protocol: {:?}",
-            self.pid, self.port_number, self.process_name, self.port_status, self.protocol
+fn warn_history_failure() {
+    if let Some(warning) = history_warning() {
+        eprintln!("{}", warning);
+    }
+}
+
+// sysinfo leaves paths empty when it isn't allowed to read them, and keeps
+// reporting the old path after the binary or directory was removed.
+fn display_path(path: Option<&Path>) -> String {
+    match path {
+        Some(path) if path.as_os_str().is_empty() => String::from("(unavailable)"),
+        Some(path) if !path.exists() => format!("{} (deleted)", path.display()),
+        Some(path) => path.display().to_string(),
+        None => String::from("(unavailable)"),
+    }
+}
+
+// A glance at the whole machine, e.g.
+//
+//   PROTO  STATUS       COUNT
+//   tcp    ESTABLISHED     40
+//   tcp    LISTEN          12
+//   udp    N/A              5
+fn display_socket_stats(manager: &Manager) {
+    let rows: Vec<[String; 3]> = manager
+        .socket_stats()
+        .into_iter()
+        .map(|(protocol, status, count)| {
+            [protocol.to_string(), status.to_string(), count.to_string()]
+        })
+        .collect();
+    let header = ["PROTO", "STATUS", "COUNT"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        println!(
+            "{:<w0$}  {:<w1$}  {:>w2$}",
+            row[0],
+            row[1],
+            row[2],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        );
+    }
+    println!("{}", manager.summary());
+}
+
+fn display_port_conflicts(manager: &Manager) {
+    let conflicts = manager.port_conflicts();
+    if conflicts.is_empty() {
+        println!("no port conflicts found");
+        return;
+    }
+
+    for (port, protocol, pids) in conflicts {
+        println!(
+            "{}",
+            red(&format!(
+                "warning: port {}/{} is claimed by {} processes",
+                port,
+                protocol,
+                pids.len()
+            ))
+        );
+        for pid in pids {
+            match manager.system_info.process(Pid::from_u32(pid)) {
+                Some(process) => println!(
+                    "  {} ({}) up {}",
+                    process_name(process.name()),
+                    pid,
+                    human_readable_date(process.run_time())
+                ),
+                None => println!("  {} (exited)", pid),
+            }
+        }
+    }
+}
+
+// `index` points into `manager.port_infos`.
+fn handle_selected(manager: &mut Manager, index: usize, details: &DetailsArgs) {
+    let actions = actions::builtin();
+    let picked = &manager.port_infos[index];
+    let selection = Select::new(
+        format!(
+            "What would you like to do with {:?}:{:?}?",
+            picked.process_name, picked.port_number,
         )
+        .as_str(),
+        actions
+            .iter()
+            .map(Box::as_ref)
+            .filter(|action| action.applies_to(picked))
+            .collect(),
+    )
+    .prompt();
+
+    if let Some(action) = answered(selection) {
+        handle_event(manager, action, index, details);
     }
 }
 
-impl Clone for PortInfo {
-    fn clone(&self) -> Self {
-        PortInfo {
-            port_number: self.port_number.clone(),
-            pid: self.pid.clone(),
-            process_name: self.process_name.clone(),
-            protocol: self.protocol.clone(),
-            port_status: self.port_status.clone(),
+// Follows up on a kill that was called off because the pid changed hands,
+// with a menu of whatever holds the port now.
+fn offer_rescan(manager: &mut Manager, port: u16, details: &DetailsArgs) {
+    let question = format!("Rescan port {}?", port);
+    if !matches!(
+        answered(Confirm::new(&question).with_default(true).prompt()),
+        Some(true)
+    ) {
+        return;
+    }
+
+    // sysinfo keeps the old name of a pid it has seen before
+    manager.system_info = System::new();
+    if let Err(err) = manager.refresh(&Filters {
+        ports: vec![port],
+        ..Filters::default()
+    }) {
+        println!("{}", err);
+        return;
+    }
+    if manager.port_infos.is_empty() {
+        println!("nothing holds port {} anymore", port);
+        return;
+    }
+    let order: Vec<usize> = (0..manager.port_infos.len()).collect();
+    let rows = order.iter().map(|i| &manager.port_infos[*i]).collect();
+    if let Some(index) = select_port_info(Select::new("Sockets on the port now:", rows), &order) {
+        handle_selected(manager, index, details);
+    }
+}
+
+fn handle_event(manager: &mut Manager, action: &dyn Action, index: usize, details: &DetailsArgs) {
+    let picked = manager.port_infos[index].clone();
+    let (pid, port) = (picked.pid, picked.port_number);
+    // drops the pid if it exited while the menu was open
+    manager.system_info.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    action.prepare(manager, pid, details);
+
+    if manager.system_info.process(Pid::from_u32(pid)).is_none() {
+        println!(
+            "Process {} is no longer running (it may have already exited).",
+            pid
+        );
+        offer_rescan(manager, port, details);
+        return;
+    }
+
+    // a typed name is harder to confirm by reflex than y/N
+    if action.kills() && picked.protected {
+        let warning = red(&format!(
+            "{} (pid {}) is a protected process, killing it can crash your machine or end your session. Type its name to go ahead:",
+            picked.process_name, picked.pid
+        ));
+        match answered(Text::new(&warning).prompt()) {
+            Some(typed) if typed.trim() == picked.process_name => {}
+            Some(_) => {
+                println!("the name didn't match, leaving it alone");
+                return;
+            }
+            None => return,
         }
     }
+
+    let mut context = Context {
+        manager,
+        picked,
+        details,
+    };
+    if action.execute(&mut context) == ActionResult::PidReused {
+        offer_rescan(context.manager, port, details);
+    }
+    warn_history_failure();
 }
 
-impl PortInfo {
-    fn display_specs(&self, proc: &Process) {
-        let local = Local::now();
-        let start_time: DateTime<Utc> = Utc.timestamp_opt(proc.start_time() as i64, 0).unwrap();
-        let tz = local.timezone();
-        let current_time = start_time.with_timezone(&tz);
+// Expects `refresh_process_details` and `sample_cpu_usage` to have run for
+// the picked pid.
+fn display_details(manager: &Manager, picked: &PortInfo, process: &Process, details: &DetailsArgs) {
+    println!("{}", picked.process_name);
+    display_specs(picked, &ProcessDetails::collect(manager, process));
+    display_process_sections(manager, process, details);
+    if details.all_pids {
+        display_socket_siblings(manager, picked);
+    }
+}
 
-        println!("in display specs!");
-        println!("Port number: {}", self.port_number);
-        println!("Port status: {}", self.port_status);
-        println!("Memory Usage: {} bytes", proc.memory());
-        println!("CPU Usage: {}%", proc.cpu_usage());
-        println!("Run time: {}", human_readable_date(proc.run_time()));
-        println!("Start time: {} UTC", current_time);
-        println!("Command: {:?}", proc.cmd());
+// What `plug info` prints for each process, with the same preconditions as
+// `display_details`.
+fn display_process(manager: &Manager, process: &Process, details: &DetailsArgs) {
+    println!("{} (pid {})", process_name(process.name()), process.pid());
+    print_process_details(&ProcessDetails::collect(manager, process));
+    display_process_sections(manager, process, details);
+}
+
+fn display_process_sections(manager: &Manager, process: &Process, details: &DetailsArgs) {
+    let pid = process.pid().as_u32();
+    display_ancestry(manager, process);
+    #[cfg(target_os = "linux")]
+    display_container(manager, pid);
+    display_socket_table(manager, pid, details.all_sockets);
+    if details.show_env {
+        print_environment(process, details.redact);
     }
 }
 
-// TODO: ***********************************************************************
-// TODO: REALLY FLESH OUT THE PORT PART FIRST, MAKE IT AWESOME, THEN WORK ON
-// TODO: THE PROCESS PART OF THE PROGRAM
-// TODO: ***********************************************************************
+fn display_socket_table(manager: &Manager, pid: u32, all: bool) {
+    let sockets = manager.ports_for_pid(pid);
+    let shown = if all {
+        sockets.len()
+    } else {
+        sockets.len().min(MAX_LISTED_SOCKETS)
+    };
 
-#[derive(Debug)]
-struct Manager {
-    // Port-part of the Manager
-    port_infos: Vec<PortInfo>,
-    by_port: HashMap<u16, Vec<usize>>,    // port -> socket indices
-    by_process: HashMap<u32, Vec<usize>>, // pid -> socket indices
-    system_info: System,
+    println!("Sockets:");
+    let rows: Vec<[String; 4]> = sockets[..shown]
+        .iter()
+        .map(|info| {
+            [
+                info.protocol.to_string(),
+                SocketAddr::new(info.local_addr, info.port_number).to_string(),
+                info.remote.map_or(String::from("-"), |remote| {
+                    match manager.peer_names.get(&remote.ip()) {
+                        Some(Some(name)) => format!("{} ({})", remote, name),
+                        _ => remote.to_string(),
+                    }
+                }),
+                info.port_status.to_string(),
+            ]
+        })
+        .collect();
+    let header = ["PROTO", "LOCAL", "REMOTE", "STATE"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        println!("  {}", cells.join("  ").trim_end());
+    }
+    if sockets.len() > shown {
+        println!("  …and {} more (use --all)", sockets.len() - shown);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn display_container(manager: &Manager, pid: u32) {
+    let Some(container) = container::container_id(pid).and_then(|id| manager.containers.get(&id))
+    else {
+        return;
+    };
+    println!(
+        "Container: {} (image {}, id {})",
+        container.name.as_deref().unwrap_or("(unnamed)"),
+        container.image.as_deref().unwrap_or("unknown"),
+        container.short_id()
+    );
+}
+
+// Shows who launched the process, e.g. `zsh(2001) → npm(4820) → node(4821)`,
+// which usually tells whose terminal or service it belongs to.
+fn display_ancestry(manager: &Manager, process: &Process) {
+    let Some(parent_pid) = process.parent() else {
+        println!("Parent: (none)");
+        return;
+    };
+    match manager.system_info.process(parent_pid) {
+        Some(parent) => println!("Parent: {} ({})", process_name(parent.name()), parent_pid),
+        None => println!("Parent: {} (exited)", parent_pid),
+    }
+
+    let mut chain: Vec<String> = manager
+        .ancestry(process.pid().as_u32())
+        .iter()
+        .take(MAX_LISTED_ANCESTORS + 1)
+        .map(|process| format!("{}({})", process_name(process.name()), process.pid()))
+        .collect();
+    chain.reverse();
+    println!("Ancestry: {}", chain.join(" → "));
+}
+
+fn display_socket_siblings(manager: &Manager, picked: &PortInfo) {
+    println!("Pids sharing this socket:");
+    for pid in &picked.socket_pids {
+        match manager.system_info.process(Pid::from_u32(*pid)) {
+            Some(process) => println!("  {} ({})", pid, process_name(process.name())),
+            None => println!("  {} (exited)", pid),
+        }
+    }
 }
-// TODO: Process-part of the Manager
-// process_info: Vec<sysinfo::Process>,
 
-impl Manager {
-    fn new() -> Manager {
-        Manager {
-            port_infos: vec![],
-            by_port: HashMap::new(),
-            by_process: HashMap::new(),
-            system_info: System::new(),
-            // process_info: vec![],
+fn display_open_files(manager: &Manager, pid: u32) {
+    let socket_fds = print_open_files(pid);
+
+    let sockets = manager.ports_for_pid(pid);
+    println!("Sockets:");
+    for info in sockets.iter().take(MAX_LISTED_FDS) {
+        match info.inode.and_then(|inode| socket_fds.get(&inode)) {
+            Some(fd) => println!("  fd {}: {}", fd, info),
+            None => println!("  {}", info),
         }
     }
+    if sockets.len() > MAX_LISTED_FDS {
+        println!("  ...and {} more", sockets.len() - MAX_LISTED_FDS);
+    }
+}
+
+// Windows can only terminate a process, so that's all it gets offered.
+fn pick_and_send_signal(manager: &Manager, process: &Process) {
+    #[cfg(unix)]
+    let signals = vec![
+        KillSignal::Term,
+        KillSignal::Hup,
+        KillSignal::Usr1,
+        KillSignal::Usr2,
+        KillSignal::Stop,
+        KillSignal::Cont,
+        KillSignal::Kill,
+    ];
+    #[cfg(not(unix))]
+    let signals = vec![KillSignal::Kill];
 
-    fn handle_selected(self, picked: PortInfo) {
-        let selection = Select::new(
-            format!(
-                "What would you like to do with {:?}:{:?}?",
-                picked.process_name, picked.port_number,
+    let prompt = format!(
+        "Signal to send to {} (pid {}):",
+        process_name(process.name()),
+        process.pid()
+    );
+    let signal = match answered(
+        Select::new(&prompt, signals)
+            .with_help_message(
+                "HUP, USR1 and USR2 make many servers reload, e.g. nginx or gunicorn",
             )
-            .as_str(),
-            create_choices_vec(),
-        )
-        .prompt();
+            .prompt(),
+    ) {
+        Some(signal) => signal,
+        None => return,
+    };
+    send_signal(
+        manager,
+        process,
+        signal.to_signal(),
+        &format!("sent {} to", signal),
+    );
+}
 
-        match selection {
-            Ok(choice) => self.handle_event(choice, picked),
-            Err(_) => println!("there was an error picking a choice"),
+// Signals go out only while the pid still belongs to the process the scan
+// listed, like kills through `Manager::kill_process_by_pid`.
+fn pid_unchanged(manager: &Manager, pid: u32) -> bool {
+    match manager.check_pid(pid) {
+        PidCheck::Unchanged => true,
+        PidCheck::Gone => {
+            println!("pid {} already exited, nothing was sent", pid);
+            false
+        }
+        PidCheck::Reused(now) => {
+            println!(
+                "pid {} belongs to another process since the scan ({}), nothing was sent",
+                pid, now
+            );
+            false
         }
     }
+}
 
-    fn handle_event(self, event: Choices, picked: PortInfo) {
-        let process = match self.system_info.process(Pid::from_u32(picked.pid)) {
-            Some(process) => process,
-            None => return,
-        };
+// Reports whether the platform supports `signal` at all, not just whether the
+// delivery itself worked.
+fn send_signal(manager: &Manager, process: &Process, signal: Signal, done: &str) {
+    if !pid_unchanged(manager, process.pid().as_u32()) {
+        return;
+    }
+    let outcome = match process.kill_with(signal) {
+        Some(true) => {
+            println!("{} {:?} (pid {})", done, process.name(), process.pid());
+            "sent"
+        }
+        Some(false) => {
+            println!("failed to send {} to pid {}", signal, process.pid());
+            "failed"
+        }
+        None => {
+            println!("{} is not supported on this platform", signal);
+            "unsupported"
+        }
+    };
+    manager.log_signal(process, signal, outcome);
+}
 
-        match event {
-            Choices::Kill => {
-                self.kill_process_by_pid(picked.pid, process);
-                println!("kill: {}", picked.process_name);
-            }
-            Choices::ViewDetails => {
-                println!("{}", picked.process_name);
-                picked.display_specs(process);
-            }
-        };
+fn restart_process(manager: &Manager, pid: u32, process: &Process) {
+    if !pid_unchanged(manager, pid) {
+        return;
     }
+    let spec = match LaunchSpec::capture(process) {
+        Ok(spec) => spec,
+        Err(reason) => {
+            println!("cannot restart pid {}: {}", pid, reason);
+            return;
+        }
+    };
+    println!("note: the new process gets plug's environment, not the original one");
 
-    fn kill_process_by_pid(&self, pid: u32, process: &Process) -> bool {
-        println!("found process to kill:");
-        println!("process: {:?}", process.name());
-        println!("process pid: {}", pid);
-        println!("process runtime: {:?}", process.run_time());
-        println!("process disk usage: {:?}", process.disk_usage());
+    // ask nicely first, platforms without SIGTERM only have the hard kill
+    let sent = process
+        .kill_with(Signal::Term)
+        .unwrap_or_else(|| process.kill());
+    manager.log_signal(
+        process,
+        Signal::Term,
+        if sent { "restart requested" } else { "failed" },
+    );
+    if !sent {
+        println!("failed to send kill message for pid: {}", pid);
+        return;
+    }
+    if !wait_for_exit(pid, RESTART_GRACE_PERIOD) {
+        println!("pid {} ignored SIGTERM, sending SIGKILL", pid);
+        let sent = process.kill();
+        manager.log_signal(
+            process,
+            Signal::Kill,
+            if sent { "restart requested" } else { "failed" },
+        );
+        if !wait_for_exit(pid, RESTART_GRACE_PERIOD) {
+            println!("pid {} is still running, not restarting it", pid);
+            return;
+        }
+    }
 
-        process.kill()
+    match spec.spawn() {
+        Ok(new_pid) => println!("restarted {:?} as pid {}", process.name(), new_pid),
+        Err(err) => println!("failed to relaunch {:?}: {}", spec.cmd, err),
     }
+}
 
-    fn kill_process_by_port(self, port: u16) {
-        // need to get processes associated with the port
-        let list_of_indexes_to_port_infos = match self.by_port.get(&port) {
-            Some(list) => list,
-            None => return,
-        };
+fn display_children(manager: &Manager, pid: u32) {
+    let children = manager.children_by_parent();
+    let root = Pid::from_u32(pid);
+
+    let direct = children.get(&root).map_or(0, |kids| kids.len());
+    if direct == 0 {
+        println!("pid {} has no child processes", pid);
+        return;
+    }
+    println!(
+        "{} direct children, {} descendants in total",
+        direct,
+        manager.process_tree(pid).len() - 1
+    );
+
+    print_process_line(manager, root, 0);
+    print_subtree(manager, &children, root, 1, &mut HashSet::from([root]));
+}
+
+// The ancestors down from init, then the process and everything below it,
+// so a worker shows up under the master that owns its port.
+fn display_tree(manager: &Manager, pid: u32) {
+    let root = Pid::from_u32(pid);
+    let mut visited = HashSet::from([root]);
+    let mut ancestors = vec![];
+    let mut parent = manager.system_info.process(root).and_then(Process::parent);
+    while let Some(pid) = parent
+        && visited.insert(pid)
+    {
+        ancestors.push(pid);
+        parent = manager.system_info.process(pid).and_then(Process::parent);
+    }
+    ancestors.reverse();
+
+    println!(
+        "{} ancestors, {} descendants",
+        ancestors.len(),
+        manager.process_tree(pid).len() - 1
+    );
+    for (depth, ancestor) in ancestors.iter().enumerate() {
+        print_process_line(manager, *ancestor, depth);
+    }
+    print_process_line(manager, root, ancestors.len());
+    print_subtree(
+        manager,
+        &manager.children_by_parent(),
+        root,
+        ancestors.len() + 1,
+        &mut visited,
+    );
+}
 
-        let mut unique_pids = HashSet::new();
-        for index in list_of_indexes_to_port_infos {
-            unique_pids.insert(self.port_infos[*index].clone().pid);
+fn print_subtree(
+    manager: &Manager,
+    children: &HashMap<Pid, Vec<Pid>>,
+    pid: Pid,
+    depth: usize,
+    visited: &mut HashSet<Pid>,
+) {
+    let Some(kids) = children.get(&pid) else {
+        return;
+    };
+    for kid in kids {
+        // a reused pid can make the parent links loop back on themselves
+        if !visited.insert(*kid) {
+            continue;
         }
+        print_process_line(manager, *kid, depth);
+        print_subtree(manager, children, *kid, depth + 1, visited);
+    }
+}
 
-        for pid in unique_pids {
-            let process = match self.system_info.process(Pid::from_u32(pid)) {
-                Some(process) => process,
-                None => return,
-            };
+fn print_process_line(manager: &Manager, pid: Pid, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let Some(process) = manager.system_info.process(pid) else {
+        println!("{}{} (exited)", indent, pid);
+        return;
+    };
+
+    let mut ports: Vec<u16> = manager
+        .ports_for_pid(pid.as_u32())
+        .iter()
+        .map(|info| info.port_number)
+        .collect();
+    ports.sort();
+    ports.dedup();
+    let sockets = if ports.is_empty() {
+        String::new()
+    } else {
+        let ports: Vec<String> = ports.iter().map(|port| port.to_string()).collect();
+        format!(" [ports: {}]", ports.join(", "))
+    };
+
+    println!(
+        "{}{} ({}) up {}{}",
+        indent,
+        process_name(process.name()),
+        pid,
+        human_readable_date(process.run_time()),
+        sockets
+    );
+}
+
+// Prints the targets of a dry run, one per line with the ports each one
+// holds, and returns how many there were:
+//
+//   Would kill 2 processes:
+//     node (pid 4821), ports 3000, 9229
+//     npm (pid 4820), no sockets
+fn print_kill_plan(manager: &Manager, outcomes: &[KillOutcome]) -> usize {
+    let targets: Vec<&KillOutcome> = outcomes
+        .iter()
+        .filter(|outcome| outcome.status == KillStatus::DryRun)
+        .collect();
+    let Some(first) = targets.first() else {
+        println!("Nothing to kill");
+        return 0;
+    };
 
-            let success = self.kill_process_by_pid(pid, process);
-            if !success {
-                println!("failed to send kill message for pid: {}", pid)
+    let count = match targets.len() {
+        1 => String::from("1 process"),
+        n => format!("{} processes", n),
+    };
+    match first.signal {
+        Signal::Kill => println!("Would kill {}:", count),
+        signal => println!("Would send {} to {}:", signal, count),
+    }
+    for target in &targets {
+        let mut ports: Vec<u16> = manager
+            .ports_for_pid(target.pid)
+            .iter()
+            .map(|info| info.port_number)
+            .collect();
+        ports.sort();
+        ports.dedup();
+        let ports: Vec<String> = ports.iter().map(|port| port.to_string()).collect();
+        let ports = match ports.len() {
+            0 => String::from("no sockets"),
+            1 => format!("port {}", ports[0]),
+            _ => format!("ports {}", ports.join(", ")),
+        };
+        println!("  {} (pid {}), {}", target.name, target.pid, ports);
+    }
+    for skipped in outcomes
+        .iter()
+        .filter(|outcome| outcome.status == KillStatus::Protected)
+    {
+        println!("{}", skipped);
+    }
+    targets.len()
+}
+
+// Rescans `ports` after a kill until they're all free or `wait` runs out,
+// and reports each one. A holder that isn't among `killed` most likely
+// got started again by a supervisor. Returns whether every port came free.
+fn confirm_ports_free(
+    manager: &mut Manager,
+    ports: &[u16],
+    killed: &HashSet<u32>,
+    wait: Duration,
+) -> bool {
+    let deadline = Instant::now() + wait;
+    let mut pending = ports.to_vec();
+    loop {
+        thread::sleep(PORT_RECHECK_INTERVAL);
+        pending.retain(|port| match manager.current_holders(*port) {
+            Some(holders) if holders.is_empty() => {
+                println!("port {} is now free", port);
+                false
             }
+            _ => true,
+        });
+        if pending.is_empty() || Instant::now() >= deadline {
+            break;
         }
     }
+
+    for port in &pending {
+        let Some(holders) = manager.current_holders(*port) else {
+            println!("could not rescan port {}", port);
+            continue;
+        };
+        let holders: Vec<String> = holders
+            .iter()
+            .map(|(pid, name)| {
+                if killed.contains(pid) {
+                    format!("{} (pid {})", name, pid)
+                } else {
+                    format!("{} (pid {}, new, restarted by a supervisor?)", name, pid)
+                }
+            })
+            .collect();
+        println!("port {} is still held by {}", port, holders.join(", "));
+    }
+    pending.is_empty()
+}
+
+// `plug kill --restart`, one target at a time so each gets its grace period.
+fn restart_targets(manager: &Manager, request: &KillRequest) {
+    for pid in manager.target_pids(request) {
+        if let Some(refused) = manager.refuse_protected(pid, Signal::Term, request.allow_protected)
+        {
+            println!("{}", refused);
+            continue;
+        }
+        match manager.system_info.process(Pid::from_u32(pid)) {
+            Some(process) => restart_process(manager, pid, process),
+            None => println!("pid {} already exited", pid),
+        }
+    }
+}
+
+// One rescan of the ports the killed processes held, for the menu.
+fn report_freed_ports(manager: &mut Manager, outcomes: &[KillOutcome]) {
+    let killed = killed_pids(outcomes);
+    let ports = manager.ports_of(&killed);
+    confirm_ports_free(manager, &ports, &killed, Duration::ZERO);
 }
 
 fn main() {
-    let address_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
-    let protocol_flags = ProtocolFlags::TCP | ProtocolFlags::UDP;
+    if let Err(err) = run() {
+        eprintln!("plug: {}", err);
+        process::exit(EXIT_SCAN_FAILED);
+    }
+}
 
-    let socket_info = match netstat2::get_sockets_info(address_flags, protocol_flags) {
-        Ok(socket_info) => socket_info,
-        Err(err) => panic!("error getting socket info: {err:?}"),
-    };
+fn run() -> Result<(), PlugError> {
+    let cli = Cli::parse();
+    if let Some(message) = cli.unused_display_flag() {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, message)
+            .exit();
+    }
+    let config = Config::load();
+
+    COLOR_ENABLED.store(
+        should_colorize(cli.no_color, config.color),
+        Ordering::Relaxed,
+    );
+    let sort = cli.sort.or(config.sort);
+    let format = cli.format;
+    let output_file = cli.output_file.as_deref();
 
-    // let mut sysinfo = System::new();
-    let mut manager = Manager::new();
-    manager.system_info.refresh_all();
+    // the first scan of whichever command runs, later ones go through `scan`
+    let collect = |filters: &Filters, format: Option<OutputFormat>| {
+        let options = CollectOptions {
+            filters: filters.clone(),
+            services: (!cli.numeric).then(services::get),
+            labels: config.port_labels(),
+            protected_names: config.protected.clone(),
+            utc: cli.utc,
+            ..CollectOptions::default()
+        };
+        let mut manager = Manager::collect(Netstat, options)
+            .inspect_err(|err| report_json_error(err, format, output_file))?;
+        manager.log_kills = !cli.no_log && config.history.unwrap_or(true);
+        Ok::<Manager, PlugError>(manager)
+    };
 
-    let proc = manager.system_info.processes();
-    let mut i = 0;
+    match cli.command {
+        Some(Command::List {
+            mut filters,
+            conflicts,
+            stats,
+        }) => {
+            config.fill_filters(&mut filters);
+            let manager = collect(&filters, format)?;
+            if conflicts {
+                display_port_conflicts(&manager);
+                return Ok(());
+            }
+            if stats {
+                display_socket_stats(&manager);
+                return Ok(());
+            }
+            let mut rows = sorted_port_infos(&manager.port_infos, sort);
+            let cut = truncate_rows(&mut rows, cli.limit);
+            let format = format.unwrap_or(OutputFormat::Plain);
+            if cli.net && !format.is_machine_readable() && output_file.is_none() {
+                print!("{}", interface_traffic());
+            }
+            if !rows.is_empty() || format.is_machine_readable() || output_file.is_some() {
+                emit(&output::render(&rows, format, cli.wide), output_file);
+            }
+            if rows.is_empty() {
+                exit_no_matches(&filters);
+            }
+            if !format.is_machine_readable() && output_file.is_none() {
+                if let Some(cut) = cut {
+                    println!("{}", cut);
+                }
+                println!("{}", manager.summary());
+            } else if let Some(cut) = cut {
+                eprintln!("{}", cut);
+            }
+            if let Some(hint) = manager.privilege_hint() {
+                eprintln!("{}", hint);
+            }
+        }
+        Some(Command::Kill(args)) => {
+            let request = match args.request() {
+                Ok(request) => request,
+                Err(err) => Cli::command()
+                    .error(
+                        clap::error::ErrorKind::ValueValidation,
+                        format!("invalid --name pattern: {}", err),
+                    )
+                    .exit(),
+            };
+            // kills by name or age confirm below, once the matches are known
+            let listed = request.name.is_some() || request.older_than.is_some();
+            if config.confirm_kill && !args.yes && !args.dry_run && !listed {
+                let question = format!("Kill {}?", args.target);
+                match answered(Confirm::new(&question).with_default(false).prompt()) {
+                    Some(true) => {}
+                    _ => return Ok(()),
+                }
+            }
 
-    for socket in socket_info.clone() {
-        for assoc_pid in socket.associated_pids.clone() {
-            let process = match proc.get(&Pid::from_u32(assoc_pid)) {
-                Some(p) => p,
-                None => continue,
+            // plug's own sockets are never a target
+            let filters = Filters {
+                self_exclude: true,
+                ..Filters::default()
+            };
+            let mut manager = collect(&filters, None)?;
+            if args.restart {
+                // relaunching needs each process's command line and working directory
+                manager.refresh_process_details(ProcessesToUpdate::All);
+            } else if !args.dry_run {
+                manager.load_launch_details();
+            }
+            if listed && !args.dry_run && !confirm_targets(&manager, &request, args.yes) {
+                return Ok(());
+            }
+            let holders: Vec<(u16, Vec<u32>)> = request
+                .ports
+                .iter()
+                .map(|port| (*port, manager.pids_on_port(*port)))
+                .collect();
+            let outcomes = if args.restart && !args.dry_run {
+                restart_targets(&manager, &request);
+                warn_history_failure();
+                vec![]
+            } else {
+                // refusals come first, the outcomes below only cover what was signalled
+                let (refused, outcomes): (Vec<_>, Vec<_>) = manager
+                    .kill_target(&request)
+                    .into_iter()
+                    .partition(|outcome| outcome.status == KillStatus::Protected);
+                for outcome in &refused {
+                    println!("{}", outcome);
+                }
+                outcomes
             };
+            if args.dry_run {
+                if print_kill_plan(&manager, &outcomes) == 0 {
+                    process::exit(EXIT_NO_MATCHES);
+                }
+                return Ok(());
+            }
+            print_kill_outcomes(&outcomes);
+            warn_history_failure();
+            match holders.as_slice() {
+                [] => {}
+                [(port, pids)] if pids.is_empty() => println!("port {} is already free", port),
+                [_] => {}
+                _ => print_port_results(&holders, &outcomes),
+            }
 
-            let (protocol, state) = match &socket.protocol_socket_info {
-                ProtocolSocketInfo::Tcp(tcp) => (ProtocolInfo::TCP, tcp.state.to_string()),
-                ProtocolSocketInfo::Udp(_) => (ProtocolInfo::UDP, String::from("N/A")),
+            let mut code = kill_exit_code(&outcomes);
+            let killed = killed_pids(&outcomes);
+            if !args.restart && !killed.is_empty() {
+                let ports = manager.ports_of(&killed);
+                let wait = Duration::from_secs(args.wait_free.unwrap_or_default());
+                if !confirm_ports_free(&mut manager, &ports, &killed, wait) && code == 0 {
+                    code = EXIT_STILL_RUNNING;
+                }
+            }
+            if code == 0 && args.strict && holders.iter().any(|(_, pids)| pids.is_empty()) {
+                code = EXIT_NO_MATCHES;
+            }
+            if code != 0 {
+                process::exit(code);
+            }
+        }
+        Some(Command::Info(args)) => {
+            let json = if args.json {
+                Some(OutputFormat::Json)
+            } else {
+                format
             };
+            let mut manager = collect(&Filters::default(), json)?;
+            let mut pids: Vec<u32> = match (args.target.port, args.target.pid) {
+                (_, Some(pid)) => vec![pid],
+                (Some(port), None) => manager.pids_on_port(port),
+                (None, None) => vec![],
+            };
+            pids.sort();
+            pids.dedup();
+            let sysinfo_pids: Vec<Pid> = pids.iter().map(|pid| Pid::from_u32(*pid)).collect();
+            manager.refresh_process_details(ProcessesToUpdate::Some(&sysinfo_pids));
+            manager.sample_cpu_usage(&pids);
+            if args.details.resolve {
+                for pid in &pids {
+                    manager.resolve_peers(*pid);
+                }
+            }
+
+            let processes: Vec<&Process> = sysinfo_pids
+                .iter()
+                .filter_map(|pid| manager.system_info.process(*pid))
+                .collect();
+            if processes.is_empty() {
+                match args.target.port {
+                    Some(port) => eprintln!("nothing is using port {}", port),
+                    None => eprintln!("no process with pid {}", pids[0]),
+                }
+                process::exit(EXIT_NO_MATCHES);
+            }
 
-            let port_info = PortInfo {
-                port_number: socket.local_port(),
-                pid: assoc_pid,
-                process_name: process.name().to_string_lossy().to_string(),
-                protocol: protocol,
-                port_status: state,
+            let pretty = format == Some(OutputFormat::JsonPretty);
+            if args.json || pretty || format == Some(OutputFormat::Json) {
+                let details: Vec<ProcessDetails> = processes
+                    .iter()
+                    .map(|process| ProcessDetails::collect(&manager, process))
+                    .collect();
+                emit(&output::to_json(&details, pretty), output_file);
+                return Ok(());
+            }
+            for (i, process) in processes.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                display_process(&manager, process, &args.details);
+            }
+        }
+        Some(Command::Wait(args)) => {
+            if !wait_for_port(&mut Manager::new(), &args)? {
+                let waited = args.timeout.unwrap_or_default();
+                if args.until_listening {
+                    eprintln!(
+                        "nothing is listening on port {} after {}s",
+                        args.port, waited
+                    );
+                } else {
+                    eprintln!("port {} is still in use after {}s", args.port, waited);
+                }
+                process::exit(EXIT_TIMEOUT);
+            }
+        }
+        Some(Command::History { count }) => match history::last(count) {
+            Ok(entries) if entries.is_empty() => println!("no signals recorded yet"),
+            Ok(entries) => print_history(&entries),
+            Err(err) => {
+                eprintln!("could not read the history log: {}", err);
+                process::exit(EXIT_NO_MATCHES);
+            }
+        },
+        Some(Command::Snapshot { action, file }) => {
+            let Some(path) = file.or_else(snapshot::path) else {
+                eprintln!("no data directory to keep the snapshot in, pass --file");
+                process::exit(EXIT_WRITE_FAILED);
             };
+            let manager = collect(&Filters::default(), None)?;
+            match action {
+                SnapshotAction::Save => {
+                    if let Err(err) = snapshot::save(&path, &manager.port_infos) {
+                        exit_write_failed(&path, err);
+                    }
+                    println!(
+                        "saved {} sockets to {}",
+                        manager.port_infos.len(),
+                        path.display()
+                    );
+                }
+                SnapshotAction::Diff => match snapshot::load(&path) {
+                    Ok(saved) => print_snapshot_diff(&snapshot::diff(&saved, &manager.port_infos)),
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        eprintln!(
+                            "no snapshot at {}, run `plug snapshot save` first",
+                            path.display()
+                        );
+                        process::exit(EXIT_NO_MATCHES);
+                    }
+                    Err(err) => {
+                        eprintln!("could not read the snapshot {}: {}", path.display(), err);
+                        process::exit(EXIT_NO_MATCHES);
+                    }
+                },
+            }
+        }
+        Some(Command::Completions { shell }) => {
+            clap_complete::generate(shell, &mut Cli::command(), "plug", &mut io::stdout());
+        }
+        Some(Command::Tui {
+            mut filters,
+            details,
+        }) => {
+            config.fill_filters(&mut filters);
+            let manager = collect(&filters, None)?;
+            if let Err(err) = tui::run(manager, sort, &details) {
+                eprintln!("the terminal UI failed: {}", err);
+            }
+        }
+        Some(Command::Watch {
+            mut filters,
+            interval,
+        }) => {
+            config.fill_filters(&mut filters);
+            let format = format.unwrap_or(OutputFormat::Plain);
+            let mut manager = collect(&filters, Some(format))?;
+            let mut changes = RescanDiff::default();
+            // only a table on a terminal is redrawn, other formats and pipes
+            // get one document per scan
+            let redraw = !format.is_machine_readable() && io::stdout().is_terminal();
+            loop {
+                let mut rows = sorted_port_infos(&manager.port_infos, sort);
+                let cut = truncate_rows(&mut rows, cli.limit);
+                // streams append records, everything else replaces the file
+                if let Some(path) = output_file {
+                    let text = output::render(&rows, format, cli.wide);
+                    let written = if format == OutputFormat::Ndjson {
+                        output::append(path, &text)
+                    } else {
+                        output::write_atomic(path, &text)
+                    };
+                    if let Err(err) = written {
+                        exit_write_failed(path, err);
+                    }
+                } else {
+                    if redraw {
+                        print!("\x1b[2J\x1b[H");
+                    }
+                    if cli.net && !format.is_machine_readable() {
+                        print!("{}", interface_traffic());
+                    }
+                    if rows.is_empty() && !format.is_machine_readable() {
+                        println!("{}", filters.describe_no_matches());
+                    } else {
+                        print!("{}", output::render(&rows, format, cli.wide));
+                    }
+                    if !format.is_machine_readable() {
+                        if let Some(cut) = cut {
+                            println!("{}", cut);
+                        }
+                        println!("{}", manager.summary());
+                        if !changes.is_empty() {
+                            println!("{} since the last scan", changes);
+                        }
+                    }
+                }
+                thread::sleep(Duration::from_secs(interval));
+                changes = manager
+                    .rescan()
+                    .inspect_err(|err| report_json_error(err, Some(format), output_file))?;
+            }
+        }
+        None => {
+            let mut filters = Filters::default();
+            config.fill_filters(&mut filters);
+            let mut manager = collect(&filters, format)?;
 
-            manager.port_infos.push(port_info);
+            // an output file has no use for the menu either
+            let format = format.or(output_file.map(|_| OutputFormat::Plain));
+            if let Some(format) = format {
+                let mut rows = sorted_port_infos(&manager.port_infos, sort);
+                let cut = truncate_rows(&mut rows, cli.limit);
+                if !rows.is_empty() || format.is_machine_readable() || output_file.is_some() {
+                    emit(&output::render(&rows, format, cli.wide), output_file);
+                }
+                if rows.is_empty() {
+                    exit_no_matches(&filters);
+                }
+                if let Some(cut) = cut {
+                    eprintln!("{}", cut);
+                }
+                return Ok(());
+            }
 
-            match manager.by_process.get_mut(&assoc_pid) {
-                Some(p_ind) => p_ind.push(i),
-                None => {
-                    manager.by_process.insert(assoc_pid, vec![i]);
+            if manager.port_infos.is_empty() {
+                // the only filters here come from the config file, offer to
+                // look past them rather than showing an empty menu
+                if !filters.is_active() {
+                    exit_no_matches(&filters);
+                }
+                eprintln!("{}", filters.describe_no_matches());
+                let clear =
+                    Confirm::new("Clear the filters from your config and show every socket?")
+                        .with_default(true)
+                        .prompt();
+                if !matches!(answered(clear), Some(true)) {
+                    process::exit(EXIT_NO_MATCHES);
+                }
+                filters = Filters::default();
+                scan(&mut manager, &filters, None, None)?;
+                if manager.port_infos.is_empty() {
+                    exit_no_matches(&filters);
                 }
             }
 
-            match manager.by_port.get_mut(&socket.local_port()) {
-                Some(l_ind) => l_ind.push(i),
-                None => {
-                    manager.by_port.insert(socket.local_port(), vec![i]);
+            if let Some(hint) = manager.privilege_hint() {
+                eprintln!("{}", hint);
+            }
+            let mut order = sorted_indices(&manager.port_infos, sort);
+            if cli.interactive_filter {
+                let query = match answered(Text::new("Filter (name/port, blank for all):").prompt())
+                {
+                    Some(query) => query,
+                    None => return Ok(()),
+                };
+                order.retain(|i| manager.port_infos[*i].matches_query(&query));
+                if order.is_empty() {
+                    eprintln!("nothing matched {:?}", query.trim());
+                    process::exit(EXIT_NO_MATCHES);
                 }
             }
+            if let Some(cut) = truncate_rows(&mut order, Some(cli.limit.unwrap_or(MAX_MENU_ROWS))) {
+                eprintln!("{}", cut);
+                if cli.limit.is_none() {
+                    eprintln!(
+                        "hint: narrow the list with --port, --name or --interactive-filter, or raise --limit"
+                    );
+                }
+            }
+            let rows = order.iter().map(|i| &manager.port_infos[*i]).collect();
+
+            let help = format!(
+                "{} -- ↑↓ to move, enter to select, type to filter",
+                manager.summary()
+            );
+            let selection = Select::new(
+                "List of processes:\nPid:Port -- Name -- Status -- Protocol -- User",
+                rows,
+            )
+            .with_help_message(&help);
 
-            i += 1;
+            if let Some(index) = select_port_info(selection, &order) {
+                handle_selected(&mut manager, index, &cli.details); // functionality goes here
+            }
         }
     }
+    Ok(())
+}
 
-    let selection = Select::new(
-        "List of processes:\nPid:Port -- Name -- Status -- Protocol",
-        manager.port_infos.clone(),
-    )
-    .prompt();
+// Polls until nothing holds `args.port`, or with --until-listening until
+// something listens on it. Returns false if the timeout ran out first.
+fn wait_for_port(manager: &mut Manager, args: &WaitArgs) -> Result<bool, PlugError> {
+    let filters = Filters {
+        ports: vec![args.port],
+        ..Filters::default()
+    };
+    let deadline = args
+        .timeout
+        .map(|secs| Instant::now() + Duration::from_secs(secs));
+
+    loop {
+        manager.refresh(&filters)?;
+        let sockets = manager.find_by_port(args.port);
+        let done = if args.until_listening {
+            sockets.iter().any(|info| info.is_listening())
+        } else {
+            sockets.is_empty()
+        };
+        if done {
+            return Ok(true);
+        }
+
+        let mut pause = Duration::from_secs(args.interval);
+        if let Some(deadline) = deadline {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(false);
+            }
+            pause = pause.min(left);
+        }
+        thread::sleep(pause);
+    }
+}
+
+// Bytes moved through each interface since boot. Per-process numbers would
+// need eBPF or similar, /proc/<pid>/io counts every read and write alike.
+fn interface_traffic() -> String {
+    let networks = Networks::new_with_refreshed_list();
+    let mut rows: Vec<[String; 3]> = networks
+        .iter()
+        .map(|(name, data)| {
+            [
+                name.clone(),
+                human_readable_bytes(data.total_received()),
+                human_readable_bytes(data.total_transmitted()),
+            ]
+        })
+        .collect();
+    rows.sort();
+
+    let header = ["INTERFACE", "RECEIVED", "SENT"];
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut out = String::new();
+    for row in std::iter::once(header.map(String::from)).chain(rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell))
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out.push('\n');
+    out
+}
+
+// Ctrl-C leaves plug the way it leaves a shell command, Esc just backs out of
+// the prompt. Any other failure ends the run, a question that can't be asked
+// can't be answered later either.
+fn answered<T>(result: InquireResult<T>) -> Option<T> {
+    match result {
+        Ok(answer) => Some(answer),
+        Err(InquireError::OperationInterrupted) => process::exit(EXIT_INTERRUPTED),
+        Err(InquireError::OperationCanceled) => None,
+        Err(InquireError::NotTTY) => {
+            eprintln!(
+                "plug: no terminal to ask on, pass --format or use a subcommand like `plug list` to print instead"
+            );
+            process::exit(EXIT_PROMPT_FAILED);
+        }
+        Err(err) => {
+            eprintln!("plug: the prompt failed: {}", err);
+            process::exit(EXIT_PROMPT_FAILED);
+        }
+    }
+}
+
+// Prints to stdout, or replaces `output_file` atomically.
+fn emit(text: &str, output_file: Option<&Path>) {
+    match output_file {
+        Some(path) => {
+            if let Err(err) = output::write_atomic(path, text) {
+                exit_write_failed(path, err);
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+fn scan(
+    manager: &mut Manager,
+    filters: &Filters,
+    format: Option<OutputFormat>,
+    output_file: Option<&Path>,
+) -> Result<(), PlugError> {
+    manager
+        .refresh(filters)
+        .inspect_err(|err| report_json_error(err, format, output_file))
+}
+
+// With a JSON format a failed scan also goes where the entries would have,
+// so readers of the output can check for an `error` key.
+fn report_json_error(err: &PlugError, format: Option<OutputFormat>, output_file: Option<&Path>) {
+    if let Some(format) = format.filter(|format| format.is_json()) {
+        emit(
+            &output::render_error(&err.to_string(), EXIT_SCAN_FAILED, format),
+            output_file,
+        );
+    }
+}
+
+fn exit_write_failed(path: &Path, err: io::Error) -> ! {
+    eprintln!("could not write {}: {}", path.display(), err);
+    process::exit(EXIT_WRITE_FAILED);
+}
 
-    match selection {
-        Ok(choice) => manager.handle_selected(choice), // functionality goes here
-        Err(_) => println!("there was an error, please try again"),
+fn exit_no_matches(filters: &Filters) -> ! {
+    eprintln!("{}", filters.describe_no_matches());
+    if filters.is_active() {
+        eprintln!(
+            "hint: loosen the filters, `protocol` and `listening` may also be set in the config file"
+        );
+    } else {
+        eprintln!("hint: sockets owned by other users can be hidden, try running with sudo");
+    }
+    process::exit(EXIT_NO_MATCHES);
+}
+
+// Shows the rows of a menu built from `order` and answers with the picked
+// one's index into `port_infos`, so nothing needs cloning to survive the prompt.
+fn select_port_info(select: Select<&PortInfo>, order: &[usize]) -> Option<usize> {
+    answered(select.raw_prompt()).map(|picked| order[picked.index])
+}
+
+// Clipboards are routinely missing (SSH sessions, headless boxes, some Wayland
+// compositors), so fall back to printing the text for the user to copy by hand.
+fn copy_to_clipboard(text: &str) {
+    match arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)) {
+        Ok(()) => println!("copied to clipboard: {}", text),
+        Err(_) => {
+            println!("no clipboard available, copy it from here instead:");
+            println!("{}", text);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn renice_process(pid: u32) {
+    let old = match priority::get(pid) {
+        Ok(nice) => nice,
+        Err(err) => {
+            println!("could not read the priority of pid {}: {}", pid, err);
+            return;
+        }
     };
+
+    let prompt = format!(
+        "New nice value for pid {} (currently {}, {} is highest priority, {} lowest):",
+        pid,
+        old,
+        priority::MIN_NICE,
+        priority::MAX_NICE
+    );
+    let new = match answered(
+        CustomType::<i32>::new(&prompt)
+            .with_error_message("Please type a whole number")
+            .with_validator(|nice: &i32| {
+                if (priority::MIN_NICE..=priority::MAX_NICE).contains(nice) {
+                    Ok(Validation::Valid)
+                } else {
+                    Ok(Validation::Invalid(
+                        "nice values range from -20 to 19".into(),
+                    ))
+                }
+            })
+            .prompt(),
+    ) {
+        Some(nice) => nice,
+        None => return,
+    };
+
+    match priority::set(pid, new) {
+        Ok(()) => println!("pid {} priority: {} -> {}", pid, old, new),
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => println!(
+            "changing the priority of pid {} needs elevated privileges (try sudo)",
+            pid
+        ),
+        Err(err) => println!("failed to change the priority of pid {}: {}", pid, err),
+    }
 }
 
-fn human_readable_date(secs: u64) -> String {
-    let days = secs / 86400;
-    let hours = (secs % 86400) / 3600;
-    let minutes = (secs % 3600) / 60;
-    let seconds = secs % 60;
+fn print_environment(process: &Process, redact: bool) {
+    println!("Environment:");
+    let vars = environ::sorted_vars(process);
+    if vars.is_empty() {
+        println!("  (empty or unreadable, processes of other users need sudo)");
+    }
+    for (key, value) in &vars {
+        if redact && environ::is_secret(key) {
+            println!("  {}={}", key, environ::MASK);
+        } else {
+            println!("  {}={}", key, value);
+        }
+    }
+}
 
-    match (days, hours, minutes, seconds) {
-        (0, 0, 0, s) => format!("{s}s"),
-        (0, 0, m, s) => format!("{m}m {s}s"),
-        (0, h, m, s) => format!("{h}h {m}m {s}s"),
-        (d, h, m, s) => format!("{d}d {h}h {m}m {s}s"),
+fn display_environment(process: &Process) {
+    let vars = environ::sorted_vars(process);
+    if vars.is_empty() {
+        println!(
+            "the environment of pid {} is empty or unreadable (processes of other users need sudo)",
+            process.pid()
+        );
+        return;
     }
+
+    let filter =
+        match answered(Text::new("Show only variables containing (blank for all):").prompt()) {
+            Some(filter) => filter.to_uppercase(),
+            None => return,
+        };
+    let vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(key, value)| {
+            key.to_uppercase().contains(&filter) || value.to_uppercase().contains(&filter)
+        })
+        .collect();
+
+    let secrets = vars
+        .iter()
+        .filter(|(key, _)| environ::is_secret(key))
+        .count();
+    let reveal = secrets > 0
+        && answered(
+            Confirm::new(&format!("Reveal {} secret-looking values?", secrets))
+                .with_default(false)
+                .prompt(),
+        )
+        .unwrap_or(false);
+
+    for (key, value) in &vars {
+        if !reveal && environ::is_secret(key) {
+            println!("{}={}", key, environ::MASK);
+        } else {
+            println!("{}={}", key, value);
+        }
+    }
+}
+
+// Headless servers have no browser to launch, print the URL so it can be
+// opened from the terminal instead.
+fn open_in_browser(url: &str) {
+    match webbrowser::open(url) {
+        Ok(()) => println!("opened {}", url),
+        Err(_) => println!("could not launch a browser, open {} manually", url),
+    }
+}
+
+// Prints a summary of the descriptors `pid` holds and returns its sockets as
+// inode -> fd, so they can be matched up with the scanned entries.
+#[cfg(target_os = "linux")]
+fn print_open_files(pid: u32) -> HashMap<u32, u32> {
+    let open_files = match fds::read(pid) {
+        Ok(open_files) => open_files,
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+            println!(
+                "reading the open files of pid {} needs elevated privileges (try sudo)",
+                pid
+            );
+            return HashMap::new();
+        }
+        Err(err) => {
+            println!("could not read the open files of pid {}: {}", pid, err);
+            return HashMap::new();
+        }
+    };
+
+    println!(
+        "File descriptors: {} ({} files, {} sockets, {} pipes, {} other)",
+        open_files.total(),
+        open_files.files.len(),
+        open_files.sockets.len(),
+        open_files.pipes,
+        open_files.other
+    );
+    println!("Files:");
+    for (fd, path) in open_files.files.iter().take(MAX_LISTED_FDS) {
+        println!("  fd {}: {}", fd, path.display());
+    }
+    if open_files.files.len() > MAX_LISTED_FDS {
+        println!("  ...and {} more", open_files.files.len() - MAX_LISTED_FDS);
+    }
+
+    open_files.sockets
+}
+
+#[cfg(not(target_os = "linux"))]
+fn print_open_files(_pid: u32) -> HashMap<u32, u32> {
+    println!("listing file descriptors is not supported on this platform");
+    HashMap::new()
 }
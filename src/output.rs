@@ -0,0 +1,403 @@
+use chrono::{SecondsFormat, Utc};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::process;
+
+use crate::{PortInfo, human_readable_date};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    /// Aligned columns with a header
+    Table,
+    /// A JSON array of entries
+    Json,
+    /// The JSON array indented for reading
+    JsonPretty,
+    /// Comma-separated values with a header row
+    Csv,
+    /// One line per entry, as shown in the interactive menu
+    Plain,
+    /// One timestamped JSON object per line, for log pipelines
+    Ndjson,
+}
+
+impl OutputFormat {
+    // Formats meant for other programs, where an empty result is still a
+    // document worth printing.
+    pub fn is_machine_readable(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json
+                | OutputFormat::JsonPretty
+                | OutputFormat::Csv
+                | OutputFormat::Ndjson
+        )
+    }
+
+    // Formats whose readers expect JSON even when plug fails.
+    pub fn is_json(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Json | OutputFormat::JsonPretty | OutputFormat::Ndjson
+        )
+    }
+}
+
+pub const COLUMNS: [&str; 9] = [
+    "PID", "PORT", "SERVICE", "PROTO", "STATUS", "ADDRESS", "USER", "AGE", "NAME",
+];
+
+// Columns added by --wide. The JSON formats always carry every field.
+pub const WIDE_COLUMNS: [&str; 2] = ["REMOTE", "STARTED"];
+
+// `wide` adds the peer and the start time to the table, CSV and plain lines.
+pub fn render(port_infos: &[PortInfo], format: OutputFormat, wide: bool) -> String {
+    match format {
+        OutputFormat::Table => render_table(port_infos, wide),
+        OutputFormat::Json => to_json(port_infos, false),
+        OutputFormat::JsonPretty => to_json(port_infos, true),
+        OutputFormat::Csv => render_csv(port_infos, wide),
+        OutputFormat::Plain => render_plain(port_infos, wide),
+        OutputFormat::Ndjson => render_ndjson(port_infos),
+    }
+}
+
+pub fn columns(info: &PortInfo) -> [String; 9] {
+    [
+        info.pid.to_string(),
+        info.port_number.to_string(),
+        info.label
+            .clone()
+            .or(info.service.clone())
+            .unwrap_or_default(),
+        info.protocol.to_string(),
+        info.port_status.to_string(),
+        info.local_addr.to_string(),
+        info.user.clone(),
+        human_readable_date(info.run_time),
+        info.process_name.clone(),
+    ]
+}
+
+pub fn wide_columns(info: &PortInfo) -> [String; 2] {
+    [
+        info.remote
+            .map_or(String::from("-"), |remote| remote.to_string()),
+        info.start_time.clone(),
+    ]
+}
+
+fn header(wide: bool) -> Vec<&'static str> {
+    let mut header = COLUMNS.to_vec();
+    if wide {
+        header.extend(WIDE_COLUMNS);
+    }
+    header
+}
+
+fn row(info: &PortInfo, wide: bool) -> Vec<String> {
+    let mut row = columns(info).to_vec();
+    if wide {
+        row.extend(wide_columns(info));
+    }
+    row
+}
+
+fn render_table(port_infos: &[PortInfo], wide: bool) -> String {
+    let header = header(wide);
+    let rows: Vec<Vec<String>> = port_infos.iter().map(|info| row(info, wide)).collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|name| name.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let mut out = table_line(&header, &widths);
+    for row in &rows {
+        out.push_str(&table_line(row, &widths));
+    }
+    out
+}
+
+fn table_line<S: AsRef<str>>(cells: &[S], widths: &[usize]) -> String {
+    let cells: Vec<String> = cells
+        .iter()
+        .zip(widths)
+        .map(|(cell, width)| format!("{:<width$}", cell.as_ref()))
+        .collect();
+    format!("{}\n", cells.join("  ").trim_end())
+}
+
+// Keys come out in field order, pretty output is indented by two spaces.
+pub fn to_json<T: Serialize + ?Sized>(value: &T, pretty: bool) -> String {
+    // only plain data goes through here, serializing it cannot fail
+    let mut out = if pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .expect("plain data serializes to JSON");
+    out.push('\n');
+    out
+}
+
+// What JSON readers get in place of the entries when plug fails, e.g.
+// {"error":"not allowed to read the socket table (...), try running with sudo","code":2}.
+pub fn render_error(message: &str, code: i32, format: OutputFormat) -> String {
+    #[derive(Serialize)]
+    struct ErrorObject<'a> {
+        error: &'a str,
+        code: i32,
+    }
+
+    to_json(
+        &ErrorObject {
+            error: message,
+            code,
+        },
+        format == OutputFormat::JsonPretty,
+    )
+}
+
+fn render_csv(port_infos: &[PortInfo], wide: bool) -> String {
+    let header: Vec<String> = header(wide)
+        .iter()
+        .map(|name| name.to_lowercase())
+        .collect();
+    let mut out = header.join(",");
+    out.push('\n');
+    for info in port_infos {
+        let row = row(info, wide);
+        let cells: Vec<Cow<str>> = row.iter().map(|cell| csv_escape(cell)).collect();
+        out.push_str(&cells.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+fn csv_escape(cell: &str) -> Cow<'_, str> {
+    if cell.contains([',', '"', '\n']) {
+        Cow::Owned(format!("\"{}\"", cell.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+fn render_plain(port_infos: &[PortInfo], wide: bool) -> String {
+    let mut out = String::from("Pid:Port -- Name -- Status -- Protocol -- User");
+    if wide {
+        out.push_str(" -- Local -- Remote -- Started");
+    }
+    out.push('\n');
+    for info in port_infos {
+        out.push_str(&info.to_string());
+        if wide {
+            let [remote, started] = wide_columns(info);
+            out.push_str(&format!(
+                " -- Local: {} -- Remote: {} -- Started: {}",
+                SocketAddr::new(info.local_addr, info.port_number),
+                remote,
+                started
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// Every record of one render shares a timestamp, so a watch tick can be
+// regrouped downstream.
+fn render_ndjson(port_infos: &[PortInfo]) -> String {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        timestamp: &'a str,
+        #[serde(flatten)]
+        info: &'a PortInfo,
+    }
+
+    let timestamp = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+    let mut out = String::new();
+    for info in port_infos {
+        let record = Record {
+            timestamp: &timestamp,
+            info,
+        };
+        out.push_str(&serde_json::to_string(&record).expect("PortInfo serializes to JSON"));
+        out.push('\n');
+    }
+    out
+}
+
+// Writes to a temporary file next to `path` and renames it over the target,
+// so readers see either the old contents or the new, never a partial file.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    create_parent_dirs(path)?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"))?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(format!(".{}.tmp", process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|()| fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+// Streams like `watch --format ndjson` keep growing the same file instead.
+pub fn append(path: &Path, contents: &str) -> io::Result<()> {
+    create_parent_dirs(path)?;
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+fn create_parent_dirs(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::create_dir_all(parent),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sockets::SocketRecord;
+    use crate::{CollectOptions, Manager, ProtocolInfo, SocketState};
+    use serde_json::Value;
+
+    // plug's own process holding a TCP listener and a UDP socket
+    fn rows() -> Vec<PortInfo> {
+        let socket = |port, protocol, state| SocketRecord {
+            local_addr: "127.0.0.1".parse().unwrap(),
+            local_port: port,
+            remote: None,
+            protocol,
+            state,
+            pids: vec![process::id()],
+            inode: None,
+        };
+        let sockets = vec![
+            socket(8080, ProtocolInfo::TCP, SocketState::Listen),
+            socket(5353, ProtocolInfo::UDP, SocketState::Stateless),
+        ];
+        Manager::collect(sockets, CollectOptions::default())
+            .unwrap()
+            .port_infos
+    }
+
+    #[test]
+    fn every_format_renders_each_row() {
+        let rows = rows();
+
+        let table = render(&rows, OutputFormat::Table, false);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let header: Vec<&str> = lines[0].split_whitespace().collect();
+        assert_eq!(header, COLUMNS);
+        assert!(lines[1].contains("8080") && lines[1].contains("tcp"));
+        assert!(lines[2].contains("5353") && lines[2].contains("udp"));
+        let wide = render(&rows, OutputFormat::Table, true);
+        assert!(wide.lines().next().unwrap().ends_with("REMOTE  STARTED"));
+
+        let csv = render(&rows, OutputFormat::Csv, false);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "pid,port,service,proto,status,address,user,age,name"
+        );
+        let pid = process::id().to_string();
+        assert!(lines[1].starts_with(&format!("{},8080,", pid)));
+        assert!(lines[1].contains(",tcp,LISTEN,127.0.0.1,"));
+        assert!(lines[2].contains(",udp,N/A,127.0.0.1,"));
+
+        let plain = render(&rows, OutputFormat::Plain, false);
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "Pid:Port -- Name -- Status -- Protocol -- User");
+        assert!(lines[1].starts_with(&format!("{}:8080 -- ", pid)));
+        assert!(lines[2].starts_with(&format!("{}:5353 -- ", pid)));
+
+        for format in [OutputFormat::Json, OutputFormat::JsonPretty] {
+            let json: Value = serde_json::from_str(&render(&rows, format, false)).unwrap();
+            let entries = json.as_array().unwrap();
+            assert_eq!(entries.len(), 2);
+            assert_eq!(entries[0]["port"], 8080);
+            assert_eq!(entries[0]["protocol"], "tcp");
+            assert_eq!(entries[1]["port"], 5353);
+            assert_eq!(entries[1]["status"], "N/A");
+        }
+        assert_eq!(render(&rows, OutputFormat::Json, false).lines().count(), 1);
+        assert!(render(&rows, OutputFormat::JsonPretty, false).contains("\n  {"));
+    }
+
+    #[test]
+    fn ndjson_is_one_object_per_line() {
+        let ndjson = render(&rows(), OutputFormat::Ndjson, false);
+        let records: Vec<Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert!(ndjson.ends_with('\n'));
+        assert_eq!(records[0]["port"], 8080);
+        assert_eq!(records[1]["port"], 5353);
+        // the fields sit next to the timestamp, not under a key of their own
+        assert!(records[0]["timestamp"].is_string());
+        assert_eq!(records[0]["timestamp"], records[1]["timestamp"]);
+        assert_eq!(records[0]["pid"], process::id());
+    }
+
+    #[test]
+    fn csv_cells_are_quoted_only_when_needed() {
+        assert_eq!(csv_escape("node"), "node");
+        assert!(matches!(csv_escape("node"), Cow::Borrowed(_)));
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_escape(""), "");
+    }
+
+    #[test]
+    fn write_atomic_leaves_no_temp_file() {
+        let dir = std::env::temp_dir().join(format!("plug-output-test-{}", process::id()));
+        let path = dir.join("nested").join("ports.json");
+
+        write_atomic(&path, "first\n").unwrap();
+        write_atomic(&path, "second\n").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+        let names: Vec<OsString> = fs::read_dir(path.parent().unwrap())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![OsString::from("ports.json")]);
+
+        // a rename over a directory fails, and takes its temp file with it
+        assert!(write_atomic(path.parent().unwrap(), "third\n").is_err());
+        let names: Vec<OsString> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![OsString::from("nested")]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,40 @@
+use std::io;
+
+// Nice values range from -20 (most favourable) to 19 (least favourable).
+pub const MIN_NICE: i32 = -20;
+pub const MAX_NICE: i32 = 19;
+
+pub fn get(pid: u32) -> io::Result<i32> {
+    // getpriority can legitimately return -1, so the only way to tell a
+    // failure apart is to clear errno beforehand and check it afterwards
+    clear_errno();
+    let priority = unsafe { libc::getpriority(libc::PRIO_PROCESS as _, pid as libc::id_t) };
+    if priority == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(0) {
+            return Err(err);
+        }
+    }
+
+    Ok(priority)
+}
+
+pub fn set(pid: u32, nice: i32) -> io::Result<()> {
+    let result = unsafe { libc::setpriority(libc::PRIO_PROCESS as _, pid as libc::id_t, nice) };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn clear_errno() {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    unsafe {
+        *libc::__errno_location() = 0;
+    }
+    #[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+    unsafe {
+        *libc::__error() = 0;
+    }
+}
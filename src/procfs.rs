@@ -0,0 +1,56 @@
+use std::fs;
+use std::io;
+
+// Bits of /proc/<pid> that sysinfo doesn't expose.
+
+// The page counts from /proc/<pid>/statm, converted to bytes. sysinfo already
+// reports the resident and virtual sizes, this adds how much of the resident
+// set is shared with other processes, mostly mapped libraries.
+pub struct Statm {
+    pub shared: u64,
+    pub text: u64,
+    pub data: u64,
+}
+
+pub fn statm(pid: u32) -> io::Result<Statm> {
+    let contents = fs::read_to_string(format!("/proc/{pid}/statm"))?;
+    // size resident shared text lib data dt
+    let pages: Vec<u64> = contents
+        .split_whitespace()
+        .map(|field| field.parse().unwrap_or(0))
+        .collect();
+    if pages.len() < 6 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "unexpected /proc/<pid>/statm format",
+        ));
+    }
+
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) }.max(0) as u64;
+    Ok(Statm {
+        shared: pages[2] * page_size,
+        text: pages[3] * page_size,
+        data: pages[5] * page_size,
+    })
+}
+
+// Includes the main thread, unlike sysinfo's task list.
+pub fn thread_count(pid: u32) -> io::Result<usize> {
+    let status = fs::read_to_string(format!("/proc/{pid}/status"))?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("Threads:"))
+        .and_then(|count| count.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no thread count in /proc/<pid>/status",
+            )
+        })
+}
+
+// sysinfo reports zeros rather than an error when /proc/<pid>/io belongs to
+// another user.
+pub fn io_readable(pid: u32) -> bool {
+    fs::File::open(format!("/proc/{pid}/io")).is_ok()
+}
@@ -0,0 +1,106 @@
+use std::borrow::Cow;
+use std::ffi::OsString;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sysinfo::{Pid, Process, ProcessStatus, ProcessesToUpdate, System};
+
+// Everything needed to start a process again, captured before it is killed
+// since its /proc entry (and so its command line) disappears with it.
+#[derive(Debug, Clone)]
+pub struct LaunchSpec {
+    pub cmd: Vec<OsString>,
+    pub cwd: Option<PathBuf>,
+}
+
+impl LaunchSpec {
+    pub fn capture(process: &Process) -> Result<LaunchSpec, String> {
+        let cmd = process.cmd().to_vec();
+        if cmd.is_empty() {
+            return Err(String::from("its command line is empty or unreadable"));
+        }
+        if let Some(exe) = process.exe()
+            && !exe.exists()
+        {
+            return Err(format!("its executable {} no longer exists", exe.display()));
+        }
+
+        Ok(LaunchSpec {
+            cmd,
+            cwd: process.cwd().map(|cwd| cwd.to_path_buf()),
+        })
+    }
+
+    // A line to paste into a POSIX shell, e.g. `cd /srv/app && node server.js --name 'my app'`.
+    pub fn shell_line(&self) -> String {
+        let cmd: Vec<String> = self
+            .cmd
+            .iter()
+            .map(|arg| shell_quote(&arg.to_string_lossy()).into_owned())
+            .collect();
+        match &self.cwd {
+            Some(cwd) => format!(
+                "cd {} && {}",
+                shell_quote(&cwd.to_string_lossy()),
+                cmd.join(" ")
+            ),
+            None => cmd.join(" "),
+        }
+    }
+
+    // Starts the command detached from plug's terminal and returns the new pid.
+    // The environment is plug's own, not the original process's.
+    pub fn spawn(&self) -> io::Result<u32> {
+        let mut command = Command::new(&self.cmd[0]);
+        command
+            .args(&self.cmd[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+
+        Ok(command.spawn()?.id())
+    }
+}
+
+// Leaves words made of safe characters alone and single-quotes the rest.
+fn shell_quote(word: &str) -> Cow<'_, str> {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !word.is_empty() && word.chars().all(safe) {
+        Cow::Borrowed(word)
+    } else {
+        Cow::Owned(format!("'{}'", word.replace('\'', "'\\''")))
+    }
+}
+
+// Polls until `pid` is gone or `timeout` elapses, returning whether it exited.
+// A zombie counts as gone, it only waits for its parent to reap it.
+pub fn wait_for_exit(pid: u32, timeout: Duration) -> bool {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        match system.process(pid) {
+            None => return true,
+            Some(process) if process.status() == ProcessStatus::Zombie => return true,
+            Some(_) => {}
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
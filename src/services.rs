@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::fs;
+use std::sync::OnceLock;
+
+use crate::ProtocolInfo;
+
+#[cfg(not(windows))]
+const SERVICES_PATH: &str = "/etc/services";
+#[cfg(windows)]
+const SERVICES_PATH: &str = r"C:\Windows\System32\drivers\etc\services";
+
+// Used when the services file is missing or empty, names as in /etc/services.
+const BUILTIN: &[(u16, ProtocolInfo, &str)] = &[
+    (21, ProtocolInfo::TCP, "ftp"),
+    (22, ProtocolInfo::TCP, "ssh"),
+    (23, ProtocolInfo::TCP, "telnet"),
+    (25, ProtocolInfo::TCP, "smtp"),
+    (53, ProtocolInfo::TCP, "domain"),
+    (53, ProtocolInfo::UDP, "domain"),
+    (67, ProtocolInfo::UDP, "bootps"),
+    (68, ProtocolInfo::UDP, "bootpc"),
+    (80, ProtocolInfo::TCP, "http"),
+    (110, ProtocolInfo::TCP, "pop3"),
+    (123, ProtocolInfo::UDP, "ntp"),
+    (143, ProtocolInfo::TCP, "imap2"),
+    (161, ProtocolInfo::UDP, "snmp"),
+    (389, ProtocolInfo::TCP, "ldap"),
+    (443, ProtocolInfo::TCP, "https"),
+    (443, ProtocolInfo::UDP, "https"),
+    (465, ProtocolInfo::TCP, "submissions"),
+    (514, ProtocolInfo::UDP, "syslog"),
+    (587, ProtocolInfo::TCP, "submission"),
+    (631, ProtocolInfo::TCP, "ipp"),
+    (993, ProtocolInfo::TCP, "imaps"),
+    (995, ProtocolInfo::TCP, "pop3s"),
+    (1433, ProtocolInfo::TCP, "ms-sql-s"),
+    (3306, ProtocolInfo::TCP, "mysql"),
+    (3389, ProtocolInfo::TCP, "ms-wbt-server"),
+    (5353, ProtocolInfo::UDP, "mdns"),
+    (5432, ProtocolInfo::TCP, "postgresql"),
+    (5672, ProtocolInfo::TCP, "amqp"),
+    (6379, ProtocolInfo::TCP, "redis"),
+    (8080, ProtocolInfo::TCP, "http-alt"),
+    (9418, ProtocolInfo::TCP, "git"),
+    (27017, ProtocolInfo::TCP, "mongodb"),
+];
+
+static SERVICES: OnceLock<ServiceNames> = OnceLock::new();
+
+// Well-known service names keyed by port and protocol, since a few ports mean
+// different things over TCP and UDP.
+pub type ServiceNames = HashMap<(u16, ProtocolInfo), String>;
+
+// Read on first use, every later call shares the same table.
+pub fn get() -> &'static ServiceNames {
+    SERVICES.get_or_init(load)
+}
+
+fn load() -> ServiceNames {
+    match fs::read_to_string(SERVICES_PATH).map(|contents| parse(&contents)) {
+        Ok(services) if !services.is_empty() => services,
+        _ => builtin(),
+    }
+}
+
+fn builtin() -> ServiceNames {
+    BUILTIN
+        .iter()
+        .map(|(port, protocol, name)| ((*port, *protocol), name.to_string()))
+        .collect()
+}
+
+// Lines look like `https  443/tcp  # comment`, aliases after the port are
+// ignored and the first name listed for a port wins.
+pub fn parse(contents: &str) -> ServiceNames {
+    let mut services = ServiceNames::new();
+
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace();
+        let (Some(name), Some(port_and_protocol)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        let Some((port, protocol)) = port_and_protocol.split_once('/') else {
+            continue;
+        };
+        let Ok(port) = port.parse() else {
+            continue;
+        };
+        let protocol = match protocol {
+            "tcp" => ProtocolInfo::TCP,
+            "udp" => ProtocolInfo::UDP,
+            _ => continue,
+        };
+
+        services
+            .entry((port, protocol))
+            .or_insert_with(|| name.to_string());
+    }
+
+    services
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_by_port_and_protocol() {
+        let services = parse(
+            "# Network services, Internet style\n\
+             ssh\t\t22/tcp\t\t\t# SSH Remote Login Protocol\n\
+             domain\t\t53/tcp\n\
+             domain\t\t53/udp\n\
+             www\t\t80/tcp\t\thttp\n\
+             http\t\t80/tcp\n\
+             \n\
+             broken\t\tport/tcp\n\
+             atalk\t\t200/ddp\n\
+             lonely\n",
+        );
+
+        assert_eq!(services.len(), 4);
+        assert_eq!(services[&(22, ProtocolInfo::TCP)], "ssh");
+        assert_eq!(services[&(53, ProtocolInfo::UDP)], "domain");
+        // the first name listed for a port wins, aliases are dropped
+        assert_eq!(services[&(80, ProtocolInfo::TCP)], "www");
+        assert!(!services.contains_key(&(80, ProtocolInfo::UDP)));
+    }
+
+    #[test]
+    fn builtin_table_knows_common_ports() {
+        let services = builtin();
+
+        assert_eq!(services[&(22, ProtocolInfo::TCP)], "ssh");
+        assert_eq!(services[&(443, ProtocolInfo::TCP)], "https");
+        assert_eq!(services[&(5353, ProtocolInfo::UDP)], "mdns");
+        assert!(!services.contains_key(&(5353, ProtocolInfo::TCP)));
+    }
+
+    #[test]
+    fn table_is_loaded_once() {
+        // from the services file or the builtin table, ssh is in both
+        assert_eq!(get()[&(22, ProtocolInfo::TCP)], "ssh");
+        assert!(std::ptr::eq(get(), get()));
+    }
+}
@@ -0,0 +1,158 @@
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{PortInfo, ProtocolInfo, output};
+
+// The part of a saved entry that a diff shows. A snapshot is the array
+// `plug list --format json` prints, the other fields are ignored on load.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Entry {
+    pub pid: u32,
+    pub port: u16,
+    pub protocol: ProtocolInfo,
+    pub name: String,
+}
+
+impl Entry {
+    fn key(&self) -> (u32, u16, ProtocolInfo) {
+        (self.pid, self.port, self.protocol)
+    }
+}
+
+// Entries match on (pid, port, protocol) alone, a process that renamed itself
+// since the snapshot is still the same holder.
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Entry {}
+
+impl Hash for Entry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl From<&PortInfo> for Entry {
+    fn from(info: &PortInfo) -> Entry {
+        Entry {
+            pid: info.pid,
+            port: info.port_number,
+            protocol: info.protocol,
+            name: info.process_name.clone(),
+        }
+    }
+}
+
+// ~/.local/share/plug/snapshot.json on Linux.
+pub fn path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "plug").map(|dirs| dirs.data_dir().join("snapshot.json"))
+}
+
+pub fn save(path: &Path, port_infos: &[PortInfo]) -> io::Result<()> {
+    output::write_atomic(path, &output::to_json(port_infos, false))
+}
+
+pub fn load(path: &Path) -> io::Result<Vec<Entry>> {
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+// Which (pid, port, protocol) holders appeared and disappeared since the
+// snapshot, each listed once and sorted by port.
+pub struct Diff {
+    pub added: Vec<Entry>,
+    pub removed: Vec<Entry>,
+}
+
+pub fn diff(saved: &[Entry], port_infos: &[PortInfo]) -> Diff {
+    let old: HashSet<&Entry> = saved.iter().collect();
+    let current: Vec<Entry> = port_infos.iter().map(Entry::from).collect();
+    let new: HashSet<&Entry> = current.iter().collect();
+    Diff {
+        added: sorted(new.difference(&old)),
+        removed: sorted(old.difference(&new)),
+    }
+}
+
+fn sorted<'a>(entries: impl Iterator<Item = &'a &'a Entry>) -> Vec<Entry> {
+    let mut entries: Vec<Entry> = entries.map(|entry| (*entry).clone()).collect();
+    entries.sort_by_key(|entry| (entry.port, entry.pid, entry.protocol as u8));
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sockets::SocketRecord;
+    use crate::{CollectOptions, Manager, SocketState};
+    use std::process;
+
+    fn entry(pid: u32, port: u16, name: &str) -> Entry {
+        Entry {
+            pid,
+            port,
+            protocol: ProtocolInfo::TCP,
+            name: String::from(name),
+        }
+    }
+
+    fn scan(ports: &[u16]) -> Manager {
+        let sockets: Vec<SocketRecord> = ports
+            .iter()
+            .map(|port| SocketRecord {
+                local_addr: "127.0.0.1".parse().unwrap(),
+                local_port: *port,
+                remote: None,
+                protocol: ProtocolInfo::TCP,
+                state: SocketState::Listen,
+                pids: vec![process::id()],
+                inode: None,
+            })
+            .collect();
+        Manager::collect(sockets, CollectOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn entries_match_on_the_key() {
+        assert_eq!(entry(10, 80, "nginx"), entry(10, 80, "nginx: worker"));
+        assert_ne!(entry(10, 80, "nginx"), entry(11, 80, "nginx"));
+        assert_ne!(entry(10, 80, "nginx"), entry(10, 443, "nginx"));
+        let udp = Entry {
+            protocol: ProtocolInfo::UDP,
+            ..entry(10, 80, "nginx")
+        };
+        assert_ne!(entry(10, 80, "nginx"), udp);
+
+        let set: HashSet<Entry> = [entry(10, 80, "a"), entry(10, 80, "b")].into();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn renamed_holder_is_not_a_change() {
+        let manager = scan(&[8080]);
+        let saved = vec![entry(process::id(), 8080, "renamed-since")];
+
+        let diff = diff(&saved, &manager.port_infos);
+        assert!(diff.added.is_empty());
+        assert!(diff.removed.is_empty());
+    }
+
+    #[test]
+    fn diff_lists_new_and_gone_holders_by_port() {
+        let manager = scan(&[9000, 8080]);
+        let saved = vec![entry(process::id(), 8080, "a"), entry(1, 7000, "b")];
+
+        let diff = diff(&saved, &manager.port_infos);
+        let ports = |entries: &[Entry]| -> Vec<u16> { entries.iter().map(|e| e.port).collect() };
+        assert_eq!(ports(&diff.added), vec![9000]);
+        assert_eq!(ports(&diff.removed), vec![7000]);
+    }
+}
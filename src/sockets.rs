@@ -0,0 +1,95 @@
+use netstat2::{AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo, SocketInfo};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::{PlugError, ProtocolInfo, SocketState};
+
+// One socket as the OS reports it, before the processes behind it are looked
+// up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketRecord {
+    pub local_addr: IpAddr,
+    pub local_port: u16,
+    pub remote: Option<SocketAddr>, // the peer of a connected TCP socket
+    pub protocol: ProtocolInfo,
+    pub state: SocketState,
+    pub pids: Vec<u32>, // empty when the owner can't be seen
+    pub inode: Option<u32>,
+}
+
+// Where `Manager` gets its sockets from. `Netstat` reads the system's socket
+// table, a `Vec<SocketRecord>` hands out a fixed set.
+pub trait SocketSource: fmt::Debug {
+    fn sockets(
+        &self,
+        address_families: AddressFamilyFlags,
+        protocols: ProtocolFlags,
+    ) -> Result<Vec<SocketRecord>, PlugError>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Netstat;
+
+impl SocketSource for Netstat {
+    fn sockets(
+        &self,
+        address_families: AddressFamilyFlags,
+        protocols: ProtocolFlags,
+    ) -> Result<Vec<SocketRecord>, PlugError> {
+        let sockets = netstat2::get_sockets_info(address_families, protocols)?;
+        Ok(sockets.into_iter().map(SocketRecord::from).collect())
+    }
+}
+
+impl From<SocketInfo> for SocketRecord {
+    fn from(socket: SocketInfo) -> SocketRecord {
+        let (protocol, state, remote) = match &socket.protocol_socket_info {
+            // listeners report 0.0.0.0:0 as their peer
+            ProtocolSocketInfo::Tcp(tcp) => (
+                ProtocolInfo::TCP,
+                SocketState::from(tcp.state),
+                (tcp.remote_port != 0).then(|| SocketAddr::new(tcp.remote_addr, tcp.remote_port)),
+            ),
+            ProtocolSocketInfo::Udp(_) => (ProtocolInfo::UDP, SocketState::Stateless, None),
+        };
+        SocketRecord {
+            local_addr: socket.local_addr(),
+            local_port: socket.local_port(),
+            remote,
+            protocol,
+            state,
+            inode: socket_inode(&socket),
+            pids: socket.associated_pids,
+        }
+    }
+}
+
+// netstat2 only reports socket inodes on Linux
+fn socket_inode(socket: &SocketInfo) -> Option<u32> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    return Some(socket.inode);
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    return None;
+}
+
+// Filtered the way the OS would filter, so a fixed set behaves like a scan.
+impl SocketSource for Vec<SocketRecord> {
+    fn sockets(
+        &self,
+        address_families: AddressFamilyFlags,
+        protocols: ProtocolFlags,
+    ) -> Result<Vec<SocketRecord>, PlugError> {
+        Ok(self
+            .iter()
+            .filter(|socket| match socket.local_addr {
+                IpAddr::V4(_) => address_families.contains(AddressFamilyFlags::IPV4),
+                IpAddr::V6(_) => address_families.contains(AddressFamilyFlags::IPV6),
+            })
+            .filter(|socket| match socket.protocol {
+                ProtocolInfo::TCP => protocols.contains(ProtocolFlags::TCP),
+                ProtocolInfo::UDP => protocols.contains(ProtocolFlags::UDP),
+            })
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,327 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Paragraph, Row, Table, TableState};
+use sysinfo::{Pid, ProcessesToUpdate, Signal};
+
+use plug::cli::DetailsArgs;
+use plug::output::{COLUMNS, columns};
+use plug::{Manager, PortInfo, SortKey, sorted_port_infos};
+
+use crate::{color_enabled, display_details, history_warning};
+
+// How often the table rescans on its own while no key is pressed.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+const KEY_HELP: &str = "↑↓ move  d details  k kill  / filter  r refresh  q quit";
+
+enum Mode {
+    Browse,
+    Filter,
+    ConfirmKill,
+    ConfirmProtectedKill, // the name has to be typed out
+}
+
+struct App {
+    manager: Manager,
+    sort: Option<SortKey>,
+    rows: Vec<PortInfo>,
+    table: TableState,
+    search: String,     // narrows the rows further, matched against every column
+    typed_name: String, // what was typed so far to confirm a protected kill
+    mode: Mode,
+    status: String,
+}
+
+pub fn run(manager: Manager, sort: Option<SortKey>, details: &DetailsArgs) -> io::Result<()> {
+    let mut app = App {
+        manager,
+        sort,
+        rows: vec![],
+        table: TableState::default(),
+        search: String::new(),
+        typed_name: String::new(),
+        mode: Mode::Browse,
+        status: String::new(),
+    };
+    // the manager arrives scanned, with the filters its rescans keep
+    app.apply_search();
+
+    let mut terminal = ratatui::init();
+    let result = app.event_loop(&mut terminal, details);
+    ratatui::restore();
+    result
+}
+
+impl App {
+    fn event_loop(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        details: &DetailsArgs,
+    ) -> io::Result<()> {
+        let mut last_refresh = Instant::now();
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let timeout = REFRESH_INTERVAL.saturating_sub(last_refresh.elapsed());
+            if !event::poll(timeout)? {
+                // don't pull the rows out from under a pending confirmation
+                if matches!(self.mode, Mode::Browse) {
+                    self.refresh();
+                }
+                last_refresh = Instant::now();
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match self.mode {
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down => self.table.select_next(),
+                    KeyCode::Up => self.table.select_previous(),
+                    KeyCode::Char('r') => {
+                        self.refresh();
+                        last_refresh = Instant::now();
+                    }
+                    KeyCode::Char('/') => {
+                        self.mode = Mode::Filter;
+                        self.status.clear();
+                    }
+                    KeyCode::Char('k') => {
+                        if let Some(picked) = self.selected() {
+                            let protected = picked.protected;
+                            self.status = self.kill_question(picked);
+                            self.typed_name.clear();
+                            self.mode = if protected {
+                                Mode::ConfirmProtectedKill
+                            } else {
+                                Mode::ConfirmKill
+                            };
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        self.show_details(terminal, details)?;
+                        last_refresh = Instant::now();
+                    }
+                    _ => {}
+                },
+                Mode::Filter => {
+                    match key.code {
+                        KeyCode::Enter => self.mode = Mode::Browse,
+                        KeyCode::Esc => {
+                            self.search.clear();
+                            self.mode = Mode::Browse;
+                        }
+                        KeyCode::Backspace => {
+                            self.search.pop();
+                        }
+                        KeyCode::Char(c) => self.search.push(c),
+                        _ => {}
+                    }
+                    self.apply_search();
+                }
+                Mode::ConfirmKill => {
+                    self.mode = Mode::Browse;
+                    self.status.clear();
+                    if key.code == KeyCode::Char('y') {
+                        self.kill_selected();
+                        self.refresh();
+                        last_refresh = Instant::now();
+                    }
+                }
+                Mode::ConfirmProtectedKill => match key.code {
+                    KeyCode::Enter => {
+                        self.mode = Mode::Browse;
+                        let confirmed = self
+                            .selected()
+                            .is_some_and(|picked| picked.process_name == self.typed_name);
+                        if confirmed {
+                            self.kill_selected();
+                            self.refresh();
+                            last_refresh = Instant::now();
+                        } else {
+                            self.status = String::from("the name didn't match, nothing was killed");
+                        }
+                    }
+                    KeyCode::Esc => {
+                        self.mode = Mode::Browse;
+                        self.status.clear();
+                    }
+                    KeyCode::Backspace => {
+                        self.typed_name.pop();
+                    }
+                    KeyCode::Char(c) => self.typed_name.push(c),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut ratatui::Frame) {
+        let [table_area, status_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+
+        let header = Row::new(COLUMNS).style(Style::new().add_modifier(Modifier::BOLD));
+        let rows = self.rows.iter().map(|info| {
+            let row = Row::new(columns(info));
+            if info.protected {
+                row.style(colored(Color::Yellow))
+            } else {
+                row
+            }
+        });
+        let widths = [
+            Constraint::Length(8),
+            Constraint::Length(6),
+            Constraint::Length(12),
+            Constraint::Length(5),
+            Constraint::Length(12),
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Length(14),
+            Constraint::Min(10),
+        ];
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let status = match self.mode {
+            Mode::Filter => Paragraph::new(format!("/{}", self.search)),
+            Mode::ConfirmKill => Paragraph::new(self.status.as_str()).style(colored(Color::Red)),
+            Mode::ConfirmProtectedKill => {
+                Paragraph::new(format!("{}{}", self.status, self.typed_name))
+                    .style(colored(Color::Red))
+            }
+            Mode::Browse if !self.status.is_empty() => Paragraph::new(self.status.as_str()),
+            Mode::Browse => Paragraph::new(format!("{} -- {}", self.manager.summary(), KEY_HELP)),
+        };
+        frame.render_widget(status, status_area);
+    }
+
+    fn refresh(&mut self) {
+        if let Err(err) = self.manager.rescan() {
+            self.status = err.to_string();
+        }
+        self.apply_search();
+    }
+
+    // Rebuilds the visible rows, keeping the cursor on the same socket when it
+    // survived the rescan.
+    fn apply_search(&mut self) {
+        let selected = self.selected().map(PortInfo::key);
+
+        let search = self.search.to_lowercase();
+        self.rows = sorted_port_infos(&self.manager.port_infos, self.sort)
+            .into_iter()
+            .filter(|info| {
+                search.is_empty()
+                    || columns(info)
+                        .iter()
+                        .any(|cell| cell.to_lowercase().contains(&search))
+            })
+            .collect();
+
+        let index = selected
+            .and_then(|key| self.rows.iter().position(|info| info.key() == key))
+            .or(if self.rows.is_empty() { None } else { Some(0) });
+        self.table
+            .select(index.map(|index| index.min(self.rows.len() - 1)));
+    }
+
+    fn selected(&self) -> Option<&PortInfo> {
+        self.table.selected().and_then(|index| self.rows.get(index))
+    }
+
+    fn kill_question(&self, picked: &PortInfo) -> String {
+        if picked.protected {
+            format!(
+                "{} (pid {}) is protected, type its name and press enter to kill it: ",
+                picked.process_name, picked.pid
+            )
+        } else {
+            format!("kill {} (pid {})? [y/N]", picked.process_name, picked.pid)
+        }
+    }
+
+    fn kill_selected(&mut self) {
+        let Some(picked) = self.selected() else {
+            return;
+        };
+        let pid = picked.pid;
+        let name = picked.process_name.clone();
+
+        self.manager.load_launch_details();
+        self.status = match self.manager.system_info.process(Pid::from_u32(pid)) {
+            Some(process) => self
+                .manager
+                .kill_process_by_pid(pid, process, Signal::Kill, false)
+                .to_string(),
+            None => format!("{} (pid {}) already exited", name, pid),
+        };
+        if let Some(warning) = history_warning() {
+            self.status = format!("{} ({})", self.status, warning);
+        }
+    }
+
+    // The details view prints plain text, so it runs on the normal screen
+    // until the user is done reading.
+    fn show_details(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        details: &DetailsArgs,
+    ) -> io::Result<()> {
+        let Some(picked) = self.selected().cloned() else {
+            return Ok(());
+        };
+
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen)?;
+
+        self.manager
+            .refresh_process_details(ProcessesToUpdate::Some(&[Pid::from_u32(picked.pid)]));
+        self.manager.sample_cpu_usage(&[picked.pid]);
+        if details.resolve {
+            self.manager.resolve_peers(picked.pid);
+        }
+        match self.manager.system_info.process(Pid::from_u32(picked.pid)) {
+            Some(process) => display_details(&self.manager, &picked, process, details),
+            None => println!(
+                "{} (pid {}) already exited",
+                picked.process_name, picked.pid
+            ),
+        }
+        print!("\npress enter to go back ");
+        io::stdout().flush()?;
+        io::stdin().read_line(&mut String::new())?;
+
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        enable_raw_mode()?;
+        terminal.clear()?;
+        self.refresh();
+        Ok(())
+    }
+}
+
+// Colors follow --no-color and NO_COLOR like the rest of plug's output, bold
+// and reverse video stay so the header and selection still stand out.
+fn colored(color: Color) -> Style {
+    if color_enabled() {
+        Style::new().fg(color)
+    } else {
+        Style::new()
+    }
+}
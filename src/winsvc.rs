@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+// The services each svchost.exe runs, by pid, e.g. 1044 -> "DcomLaunch,
+// PlugPlay, Power". Empty when tasklist can't be run, the rows then only
+// show svchost.exe.
+pub fn hosted_services() -> HashMap<u32, String> {
+    let output = Command::new("tasklist")
+        .args([
+            "/svc",
+            "/fo",
+            "csv",
+            "/nh",
+            "/fi",
+            "imagename eq svchost.exe",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(parse_line)
+            .collect(),
+        _ => HashMap::new(),
+    }
+}
+
+// "svchost.exe","1044","DcomLaunch,PlugPlay,Power", with "N/A" for a process
+// that hosts nothing.
+fn parse_line(line: &str) -> Option<(u32, String)> {
+    let fields: Vec<&str> = line.trim().trim_matches('"').split("\",\"").collect();
+    let [_, pid, services] = fields[..] else {
+        return None;
+    };
+    if services == "N/A" {
+        return None;
+    }
+    let services: Vec<&str> = services.split(',').collect();
+    Some((pid.parse().ok()?, services.join(", ")))
+}
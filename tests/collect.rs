@@ -0,0 +1,62 @@
+// Runs the real scan against sockets this test opens itself. Some sandboxes
+// can't read the socket table at all, so these only run when asked for:
+//
+//   cargo test --test collect -- --ignored
+
+use std::net::{TcpListener, UdpSocket};
+use std::process;
+
+use plug::cli::Filters;
+use plug::sockets::Netstat;
+use plug::{CollectOptions, Manager, PortInfo, ProtocolInfo, SocketState};
+
+fn collect(port: u16) -> Manager {
+    let options = CollectOptions {
+        filters: Filters {
+            ports: vec![port],
+            ..Filters::default()
+        },
+        ..CollectOptions::default()
+    };
+    Manager::collect(Netstat, options).unwrap_or_else(|err| panic!("scan failed: {}", err))
+}
+
+fn own_entry(manager: &Manager, port: u16, protocol: ProtocolInfo) -> &PortInfo {
+    let pid = process::id();
+    let entry = manager
+        .find_by_port(port)
+        .into_iter()
+        .find(|info| info.pid == pid && info.protocol == protocol)
+        .unwrap_or_else(|| panic!("no {} entry for pid {} on port {}", protocol, pid, port));
+    assert!(
+        manager.ports_for_pid(pid).contains(&entry),
+        "the pid index misses port {}",
+        port
+    );
+    entry
+}
+
+#[test]
+#[ignore = "reads the system socket table"]
+fn finds_own_tcp_listener() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let manager = collect(port);
+
+    let entry = own_entry(&manager, port, ProtocolInfo::TCP);
+    assert_eq!(entry.port_status, SocketState::Listen);
+    assert!(entry.is_listening());
+    assert_eq!(manager.pids_on_port(port), vec![process::id()]);
+}
+
+#[test]
+#[ignore = "reads the system socket table"]
+fn finds_own_udp_socket() {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    let manager = collect(port);
+
+    let entry = own_entry(&manager, port, ProtocolInfo::UDP);
+    assert_eq!(entry.port_status, SocketState::Stateless);
+    assert_eq!(manager.pids_on_port(port), vec![process::id()]);
+}